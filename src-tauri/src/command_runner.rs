@@ -0,0 +1,258 @@
+// src/command_runner.rs
+
+//! Running external programs. Setup code goes through `CommandRunner`
+//! rather than the shell plugin, so it can be tested against scripted
+//! output instead of a real Docker install.
+
+use std::fmt;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use tauri_plugin_shell::process::CommandEvent;
+use tauri_plugin_shell::ShellExt;
+
+/// A program to run, with its arguments, extra environment and input.
+#[derive(Debug, Clone, Default)]
+pub struct ShellCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Written to the program's stdin, which is then closed.
+    pub stdin: Option<String>,
+}
+
+impl ShellCommand {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self { program: program.into(), ..Self::default() }
+    }
+
+    /// `sh -c script`, for pipelines and `||` fallbacks.
+    pub fn sh(script: impl Into<String>) -> Self {
+        Self::new("sh").args(["-c".to_string(), script.into()])
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn stdin(mut self, input: impl Into<String>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+}
+
+/// The program and its arguments. Environment and stdin are left out, as
+/// they may hold secrets.
+impl fmt::Display for ShellCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a program exited and what it printed. Output is collected line by
+/// line, each line ending in `\n`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// `None` when the program was killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+#[async_trait]
+pub trait CommandRunner: Send + Sync {
+    /// Runs `command` to completion, passing each line of output to
+    /// `on_line` as it is printed. Fails only if the program could not be
+    /// run; a non-zero exit is reported in the output.
+    async fn stream(
+        &self,
+        command: &ShellCommand,
+        on_line: &(dyn Fn(OutputLine) + Send + Sync),
+    ) -> Result<CommandOutput>;
+
+    /// Runs `command` to completion.
+    async fn run(&self, command: &ShellCommand) -> Result<CommandOutput> {
+        self.stream(command, &|_| {}).await
+    }
+}
+
+/// Runs programs through the shell plugin.
+pub struct ShellRunner {
+    app: tauri::AppHandle,
+}
+
+impl ShellRunner {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait]
+impl CommandRunner for ShellRunner {
+    async fn stream(
+        &self,
+        command: &ShellCommand,
+        on_line: &(dyn Fn(OutputLine) + Send + Sync),
+    ) -> Result<CommandOutput> {
+        let (mut events, mut child) = self.app.shell()
+            .command(&command.program)
+            .args(&command.args)
+            .envs(command.env.clone())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to run {}: {}", command.program, e))?;
+
+        if let Some(input) = &command.stdin {
+            child.write(input.as_bytes())?;
+        }
+        // Dropping the child closes its stdin; the process keeps running.
+        drop(child);
+
+        let mut output = CommandOutput::default();
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) => {
+                    let line = decode_line(&bytes);
+                    push_line(&mut output.stdout, &line);
+                    on_line(OutputLine::Stdout(line));
+                }
+                CommandEvent::Stderr(bytes) => {
+                    let line = decode_line(&bytes);
+                    push_line(&mut output.stderr, &line);
+                    on_line(OutputLine::Stderr(line));
+                }
+                CommandEvent::Error(e) => log::warn!("{}: {}", command.program, e),
+                CommandEvent::Terminated(status) => {
+                    output.code = status.code;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches(['\n', '\r']).to_string()
+}
+
+fn push_line(buf: &mut String, line: &str) {
+    buf.push_str(line);
+    buf.push('\n');
+}
+
+/// A `CommandRunner` that answers from a script instead of running
+/// anything, for tests.
+#[cfg(test)]
+pub mod scripted {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use super::*;
+
+    struct Rule {
+        prefix: String,
+        replies: VecDeque<CommandOutput>,
+    }
+
+    /// Answers each command with the first rule whose prefix it starts
+    /// with, as displayed (`program arg…`). A rule gives its replies in
+    /// turn and then keeps repeating the last one. Commands no rule
+    /// matches fail to run.
+    #[derive(Default)]
+    pub struct ScriptedRunner {
+        rules: Mutex<Vec<Rule>>,
+        calls: Mutex<Vec<ShellCommand>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn on(self, prefix: &str, reply: CommandOutput) -> Self {
+            self.on_each(prefix, vec![reply])
+        }
+
+        pub fn on_each(self, prefix: &str, replies: Vec<CommandOutput>) -> Self {
+            assert!(!replies.is_empty(), "rule for `{}` has no replies", prefix);
+            self.rules.lock().unwrap().push(Rule {
+                prefix: prefix.to_string(),
+                replies: replies.into(),
+            });
+            self
+        }
+
+        /// Everything that was run, in order.
+        pub fn calls(&self) -> Vec<ShellCommand> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        /// How many commands starting with `prefix` were run.
+        pub fn count(&self, prefix: &str) -> usize {
+            self.calls().iter().filter(|c| c.to_string().starts_with(prefix)).count()
+        }
+    }
+
+    #[async_trait]
+    impl CommandRunner for ScriptedRunner {
+        async fn stream(
+            &self,
+            command: &ShellCommand,
+            on_line: &(dyn Fn(OutputLine) + Send + Sync),
+        ) -> Result<CommandOutput> {
+            self.calls.lock().unwrap().push(command.clone());
+
+            let line = command.to_string();
+            let reply = {
+                let mut rules = self.rules.lock().unwrap();
+                let rule = rules.iter_mut()
+                    .find(|rule| line.starts_with(&rule.prefix))
+                    .ok_or_else(|| anyhow!("No script for `{}`", line))?;
+                if rule.replies.len() > 1 {
+                    rule.replies.pop_front().unwrap()
+                } else {
+                    rule.replies[0].clone()
+                }
+            };
+
+            for out in reply.stdout.lines() {
+                on_line(OutputLine::Stdout(out.to_string()));
+            }
+            for err in reply.stderr.lines() {
+                on_line(OutputLine::Stderr(err.to_string()));
+            }
+            Ok(reply)
+        }
+    }
+
+    /// Exits 0 printing `stdout`.
+    pub fn ok(stdout: &str) -> CommandOutput {
+        CommandOutput { code: Some(0), stdout: stdout.to_string(), stderr: String::new() }
+    }
+
+    /// Exits with `code` printing `stderr`.
+    pub fn fail(code: i32, stderr: &str) -> CommandOutput {
+        CommandOutput { code: Some(code), stdout: String::new(), stderr: stderr.to_string() }
+    }
+}
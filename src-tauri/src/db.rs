@@ -1,14 +1,24 @@
 // src/db.rs
 
+pub mod attachments;
+pub mod backup;
+pub mod migrations;
 pub mod notes;
+pub mod revisions;
+pub mod state;
+pub mod sync;
+pub mod tags;
+pub mod trash;
 
-use mysql_async::{Pool, Opts};
-use mysql_async::prelude::Queryable;
+use mysql_async::{Pool, Opts, OptsBuilder};
 use anyhow::Result;
 use dotenv::dotenv;
 use std::env;
+use crate::timestamps;
 
-pub async fn create_database_pool() -> Result<Pool> {
+pub use state::{DatabaseConfigUpdate, DatabaseState, DatabaseStatus};
+
+pub fn database_opts() -> Result<Opts> {
     // Load environment variables from .env
     dotenv().ok();
 
@@ -19,12 +29,31 @@ pub async fn create_database_pool() -> Result<Pool> {
     });
 
     // Parse the database URL into connection options
-    let opts = Opts::from_url(&database_url)?;
+    Ok(Opts::from_url(&database_url)?)
+}
+
+pub async fn create_database_pool(opts: Opts) -> Result<Pool> {
+    // Pin the session time zone on every pooled connection so TIMESTAMP
+    // values are read and written as UTC.
+    let opts = OptsBuilder::from_opts(opts).init(vec![timestamps::SESSION_TIME_ZONE]);
     let pool = Pool::new(opts);
 
-    // Ensure the notes table exists by running the table creation query
-    let mut conn = pool.get_conn().await?;
-    conn.query_drop(crate::models::CREATE_NOTES_TABLE).await?;
+    // Bring the schema up to date. This also verifies the credentials
+    // before the pool is handed out.
+    let applied = match migrations::MigrationRunner::new(pool.clone()).run_pending().await {
+        Ok(applied) => applied,
+        Err(e) => {
+            // Close the connections the failed attempt opened; lazy
+            // reconnects would otherwise leave them behind on every retry.
+            if let Err(disconnect) = pool.disconnect().await {
+                log::warn!("Failed to disconnect pool after failed migrations: {}", disconnect);
+            }
+            return Err(e);
+        }
+    };
+    if !applied.is_empty() {
+        log::info!("Applied schema migrations: {:?}", applied);
+    }
 
     Ok(pool)
 }
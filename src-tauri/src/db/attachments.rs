@@ -0,0 +1,274 @@
+// src/db/attachments.rs
+
+//! Files attached to notes.
+//!
+//! Contents are stored once per distinct file under the app data dir's
+//! `attachments` folder, named by their SHA-256 (`ab/abcdef…`). The
+//! `note_attachments` table holds the name, type and size each note knows
+//! the file by. Deleting an attachment or purging its note only removes
+//! rows; blobs no row refers to any more are removed by `run_attachment_gc`.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use mysql_async::{prelude::*, Pool};
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+use tauri_plugin_opener::OpenerExt;
+use crate::error::AppError;
+use crate::models::{Attachment, DeleteResult};
+use super::{tags, DatabaseState};
+
+/// Largest file that can be attached.
+pub const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Blobs younger than this are never collected, so a file written just
+/// before its row is inserted is not mistaken for an orphan.
+const GC_GRACE: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+pub struct AttachmentRepository {
+    pool: Pool,
+    dir: PathBuf,
+}
+
+impl AttachmentRepository {
+    pub fn new(pool: Pool, dir: PathBuf) -> Self {
+        Self { pool, dir }
+    }
+
+    /// Copies the file at `source` into the blob store and attaches it to
+    /// the note. The note must exist and not be in the trash.
+    pub async fn add_attachment(&self, note_id: i64, source: &Path) -> Result<Attachment, AppError> {
+        let file_name = source.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| AppError::validation("Attachment path must name a file"))?;
+        let mime_type = mime_guess::from_path(source)
+            .first_raw()
+            .unwrap_or(DEFAULT_MIME_TYPE)
+            .to_string();
+
+        let mut conn = self.pool.get_conn().await?;
+        tags::ensure_note_exists(&mut conn, note_id).await?;
+
+        let (sha256, size_bytes) = self.store_blob(source).await?;
+
+        conn.exec_drop(
+            r"INSERT INTO note_attachments (note_id, file_name, mime_type, size_bytes, sha256)
+              VALUES (:note_id, :file_name, :mime_type, :size_bytes, :sha256)",
+            params! {
+                "note_id" => note_id,
+                "file_name" => &file_name,
+                "mime_type" => &mime_type,
+                "size_bytes" => size_bytes,
+                "sha256" => &sha256,
+            },
+        ).await?;
+        let id = conn.last_insert_id().unwrap_or_default() as i64;
+
+        self.get_attachment(id).await
+    }
+
+    /// Attachments of a note, oldest first.
+    pub async fn list_attachments(&self, note_id: i64) -> Result<Vec<Attachment>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, note_id, file_name, mime_type, size_bytes, sha256, created_at
+                      FROM note_attachments WHERE note_id = :note_id
+                      ORDER BY created_at, id";
+        Ok(conn.exec(query, params! { "note_id" => note_id }).await?)
+    }
+
+    pub async fn get_attachment(&self, id: i64) -> Result<Attachment, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, note_id, file_name, mime_type, size_bytes, sha256, created_at
+                      FROM note_attachments WHERE id = :id";
+        let found: Option<Attachment> = conn.exec_first(query, params! { "id" => id }).await?;
+
+        found.ok_or_else(|| AppError::not_found("Attachment"))
+    }
+
+    /// Removes the attachment from its note. The blob stays until the next
+    /// garbage collection, since another note may share it.
+    pub async fn delete_attachment(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        conn.exec_drop(r"DELETE FROM note_attachments WHERE id = :id", params! { "id" => id }).await?;
+
+        Ok(DeleteResult { id, rows_affected: conn.affected_rows() })
+    }
+
+    /// Where the blob with this SHA-256 is stored.
+    pub fn blob_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(&sha256[..2]).join(sha256)
+    }
+
+    /// Deletes blobs that no attachment refers to. Returns how many were
+    /// removed.
+    pub async fn collect_garbage(&self) -> Result<u64, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+        let referenced: Vec<String> = conn.query(r"SELECT DISTINCT sha256 FROM note_attachments").await?;
+        drop(conn);
+
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || remove_orphans(&dir, &referenced))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+    }
+
+    /// Streams `source` into the blob store, hashing it on the way. Returns
+    /// the SHA-256 and size of the contents.
+    async fn store_blob(&self, source: &Path) -> Result<(String, u64), AppError> {
+        let source = source.to_path_buf();
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || write_blob(&dir, &source))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?
+    }
+}
+
+/// The repository for the current pool, storing blobs under the app data
+/// dir.
+pub async fn repository(app: &tauri::AppHandle) -> Result<AttachmentRepository, AppError> {
+    let pool = app.state::<DatabaseState>().pool().await?;
+    Ok(AttachmentRepository::new(pool, attachment_dir(app)?))
+}
+
+/// Opens an attachment with the system's default app for its type. The
+/// blob is copied out under its original file name first, so the app that
+/// opens it can tell what kind of file it is.
+pub async fn open_attachment(app: &tauri::AppHandle, id: i64) -> Result<(), AppError> {
+    let repo = repository(app).await?;
+    let attachment = repo.get_attachment(id).await?;
+
+    let dir = app.path().app_cache_dir()?.join("attachments").join(id.to_string());
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| AppError::io(&dir, e))?;
+    // The stored name came from `Path::file_name`, but never trust it to
+    // stay inside `dir`.
+    let file_name = Path::new(&attachment.file_name)
+        .file_name()
+        .ok_or_else(|| AppError::Internal("Attachment has no file name".into()))?;
+    let path = dir.join(file_name);
+    let blob = repo.blob_path(&attachment.sha256);
+    tokio::fs::copy(&blob, &path).await.map_err(|e| AppError::io(&blob, e))?;
+
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| AppError::Internal(format!("Failed to open {}: {}", path.display(), e)))
+}
+
+/// Periodically removes blobs no attachment refers to. Runs for the
+/// lifetime of the app and skips runs while MySQL is unavailable.
+pub async fn run_attachment_gc(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(GC_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let Some(pool) = app.state::<DatabaseState>().reachable_pool().await else {
+            continue;
+        };
+        let dir = match attachment_dir(&app) {
+            Ok(dir) => dir,
+            Err(e) => {
+                log::warn!("Attachment garbage collection skipped: {}", e);
+                continue;
+            }
+        };
+        match AttachmentRepository::new(pool, dir).collect_garbage().await {
+            Ok(0) => {},
+            Ok(removed) => log::info!("Removed {} unreferenced attachment file(s)", removed),
+            Err(e) => log::warn!("Attachment garbage collection failed: {}", e),
+        }
+    }
+}
+
+fn attachment_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app.path().app_data_dir()?.join("attachments"))
+}
+
+fn write_blob(dir: &Path, source: &Path) -> Result<(String, u64), AppError> {
+    let metadata = std::fs::metadata(source).map_err(|e| AppError::io(source, e))?;
+    if !metadata.is_file() {
+        return Err(AppError::validation(format!("{} is not a file", source.display())));
+    }
+    if metadata.len() > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::validation(format!(
+            "Attachments are limited to {} MB",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        )));
+    }
+
+    std::fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e))?;
+    let partial = dir.join(format!(".{}.partial", uuid::Uuid::new_v4()));
+    let result = copy_hashed(source, &partial).and_then(|(sha256, size)| {
+        let shard = dir.join(&sha256[..2]);
+        let blob = shard.join(&sha256);
+        std::fs::create_dir_all(&shard).map_err(|e| AppError::io(&shard, e))?;
+        // Renaming over an existing blob refreshes its age, which keeps a
+        // concurrent garbage collection away from it.
+        std::fs::rename(&partial, &blob).map_err(|e| AppError::io(&blob, e))?;
+        Ok((sha256, size))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn copy_hashed(source: &Path, dest: &Path) -> Result<(String, u64), AppError> {
+    let mut input = std::fs::File::open(source).map_err(|e| AppError::io(source, e))?;
+    let mut output = std::fs::File::create(dest).map_err(|e| AppError::io(dest, e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let read = input.read(&mut buf).map_err(|e| AppError::io(source, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        output.write_all(&buf[..read]).map_err(|e| AppError::io(dest, e))?;
+        size += read as u64;
+    }
+    output.sync_all().map_err(|e| AppError::io(dest, e))?;
+
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+fn remove_orphans(dir: &Path, referenced: &[String]) -> Result<u64, AppError> {
+    let shards = match std::fs::read_dir(dir) {
+        Ok(shards) => shards,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(AppError::io(dir, e)),
+    };
+    let referenced: std::collections::HashSet<&str> = referenced.iter().map(String::as_str).collect();
+    let cutoff = SystemTime::now() - GC_GRACE;
+
+    let mut removed = 0;
+    for shard in shards.flatten() {
+        let Ok(blobs) = std::fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for blob in blobs.flatten() {
+            let name = blob.file_name();
+            if referenced.contains(name.to_string_lossy().as_ref()) {
+                continue;
+            }
+            let recent = blob.metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map_or(true, |modified| modified > cutoff);
+            if recent {
+                continue;
+            }
+            match std::fs::remove_file(blob.path()) {
+                Ok(()) => removed += 1,
+                Err(e) => log::warn!("Failed to remove {}: {}", blob.path().display(), e),
+            }
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,410 @@
+// src/db/backup.rs
+
+//! Logical backups of the app schema, taken with `mysqldump` inside the
+//! MySQL container.
+//!
+//! Each backup is a gzipped dump, `<database>-<UTC time>.sql.gz`, in the app
+//! data dir's `backups` folder, next to a `.sha256` file in `sha256sum`
+//! format. Only the newest `keep` backups are kept.
+
+use std::env;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
+use dotenv::dotenv;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::Manager;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use crate::error::AppError;
+use crate::setup::MYSQL_CONTAINER;
+use super::{migrations::MigrationRunner, DatabaseState};
+
+const DEFAULT_KEEP: u32 = 7;
+const BACKUP_EXTENSION: &str = ".sql.gz";
+const CHECKSUM_EXTENSION: &str = ".sha256";
+/// Milliseconds keep a manual backup, the safety backup taken by a restore
+/// and the scheduled one apart; `free_name` handles any remaining clash.
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+/// Names of backups taken before milliseconds were added.
+const LEGACY_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Last line `mysqldump` writes; a dump without it was cut short.
+const DUMP_TRAILER: &str = "-- Dump completed";
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SCHEDULE_EVERY: chrono::Duration = chrono::Duration::days(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// How many backups to keep. Seeded from `BACKUP_KEEP` and adjustable at
+/// runtime.
+pub struct BackupSettings {
+    keep: AtomicU32,
+    // Backups and restores never overlap.
+    running: Mutex<()>,
+}
+
+impl BackupSettings {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let keep = env::var("BACKUP_KEEP")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&keep| keep > 0)
+            .unwrap_or(DEFAULT_KEEP);
+
+        Self {
+            keep: AtomicU32::new(keep),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn keep(&self) -> u32 {
+        self.keep.load(Ordering::Relaxed)
+    }
+
+    pub fn set_keep(&self, keep: u32) {
+        self.keep.store(keep, Ordering::Relaxed);
+    }
+}
+
+/// Dumps the app schema into a new backup and rotates old ones out.
+pub async fn backup_database(app: &tauri::AppHandle) -> Result<BackupInfo, AppError> {
+    let settings = app.state::<BackupSettings>();
+    let _running = settings.running.lock().await;
+    create_backup(app, settings.keep()).await
+}
+
+/// Replaces the app schema with the contents of a backup. The backup is
+/// verified first, and the current data is backed up before it is
+/// overwritten.
+pub async fn restore_database(app: &tauri::AppHandle, file_name: &str) -> Result<(), AppError> {
+    let settings = app.state::<BackupSettings>();
+    let _running = settings.running.lock().await;
+
+    let dir = backup_dir(app)?;
+    let backup = find_backup(&dir, file_name).await?;
+    // Read before the safety backup below, whose rotation may remove it.
+    let dump = read_verified(&dir, &backup).await?;
+
+    let safety = create_backup(app, settings.keep().max(2)).await?;
+    log::info!("Backed up current data to {} before restoring {}", safety.file_name, backup.file_name);
+
+    let opts = app.state::<DatabaseState>().opts().await?;
+    let mut child = mysql_command("mysql", &opts)
+        .arg(opts.db_name().unwrap_or_default())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(docker_error)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&dump).await.map_err(|e| AppError::Internal(format!("Failed to send backup to MySQL: {}", e)))?;
+        // Closing stdin ends the session.
+        drop(stdin);
+    }
+    let output = child.wait_with_output().await.map_err(docker_error)?;
+    if !output.status.success() {
+        return Err(AppError::Internal(format!(
+            "Restore failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // The dump may predate later migrations.
+    let pool = app.state::<DatabaseState>().pool().await?;
+    MigrationRunner::new(pool).run_pending().await?;
+
+    log::info!("Restored database from {}", backup.file_name);
+    Ok(())
+}
+
+/// Backups in the app data dir, newest first.
+pub async fn list_backups(app: &tauri::AppHandle) -> Result<Vec<BackupInfo>, AppError> {
+    list_in(&backup_dir(app)?).await
+}
+
+/// Takes a backup once a day while MySQL is reachable. Checks hourly
+/// against the newest backup, so the schedule survives restarts. Runs for
+/// the lifetime of the app.
+pub async fn run_backup_scheduler(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if app.state::<DatabaseState>().reachable_pool().await.is_none() {
+            continue;
+        }
+        let due = match list_backups(&app).await {
+            Ok(backups) => backups.first().map_or(true, |newest| Utc::now() - newest.created_at >= SCHEDULE_EVERY),
+            Err(e) => {
+                log::warn!("Failed to list backups: {}", e);
+                continue;
+            }
+        };
+        if !due {
+            continue;
+        }
+
+        match backup_database(&app).await {
+            Ok(backup) => log::info!("Scheduled backup written to {}", backup.file_name),
+            Err(e) => log::warn!("Scheduled backup failed: {}", e),
+        }
+    }
+}
+
+async fn create_backup(app: &tauri::AppHandle, keep: u32) -> Result<BackupInfo, AppError> {
+    let opts = app.state::<DatabaseState>().opts().await?;
+    let db_name = opts.db_name().unwrap_or_default().to_string();
+
+    let output = mysql_command("mysqldump", &opts)
+        .args(["--single-transaction", "--no-tablespaces", "--set-gtid-purged=OFF", "--triggers"])
+        .arg(&db_name)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(docker_error)?;
+    if !output.status.success() {
+        return Err(AppError::DatabaseUnavailable(format!(
+            "mysqldump failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    write_backup(&backup_dir(app)?, &db_name, output.stdout, keep).await
+}
+
+/// Compresses a dump into a new backup in `dir` and rotates old ones out.
+async fn write_backup(dir: &Path, db_name: &str, dump: Vec<u8>, keep: u32) -> Result<BackupInfo, AppError> {
+    tokio::fs::create_dir_all(dir).await.map_err(|e| AppError::io(dir, e))?;
+    let (file_name, created_at) = free_name(dir, db_name).await;
+
+    let compressed = tokio::task::spawn_blocking(move || {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&dump)?;
+        encoder.finish()
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .map_err(|e| AppError::Internal(format!("Failed to compress backup: {}", e)))?;
+
+    let sha256 = hex::encode(Sha256::digest(&compressed));
+    let path = dir.join(&file_name);
+    tokio::fs::write(&path, &compressed).await.map_err(|e| AppError::io(&path, e))?;
+    let checksum_path = checksum_path(&path);
+    tokio::fs::write(&checksum_path, format!("{}  {}\n", sha256, file_name))
+        .await
+        .map_err(|e| AppError::io(&checksum_path, e))?;
+
+    rotate(dir, keep).await;
+
+    Ok(BackupInfo {
+        file_name,
+        created_at,
+        size_bytes: compressed.len() as u64,
+        sha256,
+    })
+}
+
+/// A backup file name for now that is not taken yet, and its time. On a
+/// clash the time moves on a millisecond, so names still sort by time.
+async fn free_name(dir: &Path, db_name: &str) -> (String, DateTime<Utc>) {
+    // Matches what `parse_created_at` reads back from the name.
+    let mut created_at = Utc::now().trunc_subsecs(3);
+    loop {
+        let file_name = format!("{}-{}{}", db_name, created_at.format(TIMESTAMP_FORMAT), BACKUP_EXTENSION);
+        if !tokio::fs::try_exists(dir.join(&file_name)).await.unwrap_or(false) {
+            return (file_name, created_at);
+        }
+        created_at += chrono::Duration::milliseconds(1);
+    }
+}
+
+/// Checks the backup against its checksum and that it decompresses to a
+/// complete dump, then returns the SQL.
+async fn read_verified(dir: &Path, backup: &BackupInfo) -> Result<Vec<u8>, AppError> {
+    let path = dir.join(&backup.file_name);
+    let compressed = tokio::fs::read(&path).await.map_err(|e| AppError::io(&path, e))?;
+
+    let actual = hex::encode(Sha256::digest(&compressed));
+    if actual != backup.sha256 {
+        return Err(AppError::validation(format!(
+            "Backup {} is corrupt: checksum does not match",
+            backup.file_name
+        )));
+    }
+
+    let dump = tokio::task::spawn_blocking(move || {
+        let mut dump = Vec::new();
+        GzDecoder::new(compressed.as_slice()).read_to_end(&mut dump).map(|_| dump)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?
+    .map_err(|e| AppError::validation(format!("Backup {} cannot be decompressed: {}", backup.file_name, e)))?;
+
+    let tail = &dump[dump.len().saturating_sub(256)..];
+    if !String::from_utf8_lossy(tail).contains(DUMP_TRAILER) {
+        return Err(AppError::validation(format!("Backup {} is incomplete", backup.file_name)));
+    }
+    Ok(dump)
+}
+
+/// `docker exec` running a MySQL client tool as the app user. The password
+/// is passed through the environment so it stays off the command line.
+fn mysql_command(tool: &str, opts: &mysql_async::Opts) -> Command {
+    let mut command = Command::new("docker");
+    command
+        .args(["exec", "-i", "-e", "MYSQL_PWD", MYSQL_CONTAINER, tool])
+        .arg(format!("--user={}", opts.user().unwrap_or("root")))
+        .env("MYSQL_PWD", opts.pass().unwrap_or_default())
+        .kill_on_drop(true);
+    command
+}
+
+pub fn backup_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app.path().app_data_dir()?.join("backups"))
+}
+
+/// Looks a backup up by name among the listed ones, so a caller cannot
+/// point outside the backup dir.
+async fn find_backup(dir: &Path, file_name: &str) -> Result<BackupInfo, AppError> {
+    list_in(dir).await?
+        .into_iter()
+        .find(|b| b.file_name == file_name)
+        .ok_or_else(|| AppError::not_found("Backup"))
+}
+
+async fn list_in(dir: &Path) -> Result<Vec<BackupInfo>, AppError> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io(dir, e)),
+    };
+
+    let mut backups = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| AppError::io(dir, e))? {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(created_at) = parse_created_at(&file_name) else {
+            continue;
+        };
+        let size_bytes = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+        // A backup without its checksum cannot be verified; it is listed
+        // with an empty checksum so restoring it fails.
+        let sha256 = tokio::fs::read_to_string(checksum_path(&entry.path()))
+            .await
+            .ok()
+            .and_then(|text| text.split_whitespace().next().map(str::to_lowercase))
+            .unwrap_or_default();
+
+        backups.push(BackupInfo { file_name, created_at, size_bytes, sha256 });
+    }
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    Ok(backups)
+}
+
+/// Deletes all but the newest `keep` backups. Failures are logged; a stale
+/// backup is not worth failing the new one over.
+async fn rotate(dir: &Path, keep: u32) {
+    let backups = match list_in(dir).await {
+        Ok(backups) => backups,
+        Err(e) => {
+            log::warn!("Failed to list backups for rotation: {}", e);
+            return;
+        }
+    };
+
+    for old in backups.iter().skip(keep as usize) {
+        let path = dir.join(&old.file_name);
+        for path in [checksum_path(&path), path] {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                log::warn!("Failed to remove old backup {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Reads the time out of `<database>-<time>.sql.gz`.
+fn parse_created_at(file_name: &str) -> Option<DateTime<Utc>> {
+    let stem = file_name.strip_suffix(BACKUP_EXTENSION)?;
+    let (_, time) = stem.rsplit_once('-')?;
+    NaiveDateTime::parse_from_str(time, TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(time, LEGACY_TIMESTAMP_FORMAT))
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+fn checksum_path(backup: &Path) -> PathBuf {
+    let mut name = backup.as_os_str().to_owned();
+    name.push(CHECKSUM_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn docker_error(e: std::io::Error) -> AppError {
+    AppError::DatabaseUnavailable(format!("Failed to run docker: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DUMP: &[u8] = b"CREATE TABLE notes (id INT);\n-- Dump completed on 2026-10-17 12:00:00\n";
+
+    #[tokio::test]
+    async fn back_to_back_backups_do_not_overwrite_each_other() {
+        let dir = TempDir::new().unwrap();
+
+        let first = write_backup(dir.path(), "app_db", DUMP.to_vec(), 7).await.unwrap();
+        let second = write_backup(dir.path(), "app_db", b"other\n-- Dump completed\n".to_vec(), 7).await.unwrap();
+
+        assert_ne!(first.file_name, second.file_name);
+        let listed = list_in(dir.path()).await.unwrap();
+        assert_eq!(
+            listed.iter().map(|b| b.file_name.as_str()).collect::<Vec<_>>(),
+            vec![second.file_name.as_str(), first.file_name.as_str()]
+        );
+        assert_eq!(read_verified(dir.path(), &listed[1]).await.unwrap(), DUMP);
+    }
+
+    #[tokio::test]
+    async fn a_taken_name_moves_on_a_millisecond() {
+        let dir = TempDir::new().unwrap();
+        let (name, created_at) = free_name(dir.path(), "app_db").await;
+        std::fs::create_dir_all(dir.path()).unwrap();
+        std::fs::write(dir.path().join(&name), b"").unwrap();
+
+        let (next, next_at) = free_name(dir.path(), "app_db").await;
+
+        assert_ne!(next, name);
+        assert!(next_at > created_at);
+        assert_eq!(parse_created_at(&next), Some(next_at));
+    }
+
+    #[test]
+    fn parses_names_with_and_without_milliseconds() {
+        assert_eq!(
+            parse_created_at("app_db-20261017T120000.250Z.sql.gz").unwrap().to_rfc3339(),
+            "2026-10-17T12:00:00.250+00:00"
+        );
+        assert_eq!(
+            parse_created_at("app_db-20261017T120000Z.sql.gz").unwrap().to_rfc3339(),
+            "2026-10-17T12:00:00+00:00"
+        );
+    }
+}
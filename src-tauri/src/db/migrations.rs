@@ -0,0 +1,356 @@
+// src/db/migrations.rs
+
+use mysql_async::{prelude::*, Pool, Conn};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// A single schema change. `up` and `down` hold one SQL statement per entry,
+/// since MySQL cannot run DDL inside a transaction anyway.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub up: &'static [&'static str],
+    pub down: &'static [&'static str],
+}
+
+impl Migration {
+    /// SHA-256 of the `up` statements. Editing a migration that has already
+    /// been applied changes this and is reported as a checksum mismatch.
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        for statement in self.up {
+            hasher.update(statement.trim().as_bytes());
+            hasher.update(b"\n;\n");
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Every migration in the order it must be applied. Versions are never
+/// reused or reordered; add new entries at the end.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_notes",
+        up: &[r#"
+CREATE TABLE IF NOT EXISTS notes (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    content TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+)"#],
+        down: &["DROP TABLE IF EXISTS notes"],
+    },
+    Migration {
+        version: 2,
+        name: "add_notes_fulltext_index",
+        up: &["ALTER TABLE notes ADD FULLTEXT INDEX ft_notes_title_content (title, content)"],
+        down: &["ALTER TABLE notes DROP INDEX ft_notes_title_content"],
+    },
+    Migration {
+        version: 3,
+        name: "add_notes_sort_indexes",
+        up: &[
+            "CREATE INDEX idx_notes_created_at ON notes (created_at, id)",
+            "CREATE INDEX idx_notes_updated_at ON notes (updated_at, id)",
+            "CREATE INDEX idx_notes_title ON notes (title, id)",
+        ],
+        down: &[
+            "DROP INDEX idx_notes_title ON notes",
+            "DROP INDEX idx_notes_updated_at ON notes",
+            "DROP INDEX idx_notes_created_at ON notes",
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "create_tags",
+        up: &[
+            r#"
+CREATE TABLE IF NOT EXISTS tags (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_tags_name (name)
+)"#,
+            r#"
+CREATE TABLE IF NOT EXISTS note_tags (
+    note_id BIGINT NOT NULL,
+    tag_id BIGINT NOT NULL,
+    PRIMARY KEY (note_id, tag_id),
+    KEY idx_note_tags_tag (tag_id),
+    CONSTRAINT fk_note_tags_note FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE,
+    CONSTRAINT fk_note_tags_tag FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+)"#,
+        ],
+        down: &[
+            "DROP TABLE IF EXISTS note_tags",
+            "DROP TABLE IF EXISTS tags",
+        ],
+    },
+    Migration {
+        version: 5,
+        name: "add_notes_deleted_at",
+        up: &[
+            "ALTER TABLE notes ADD COLUMN deleted_at TIMESTAMP NULL DEFAULT NULL",
+            "CREATE INDEX idx_notes_deleted_at ON notes (deleted_at)",
+        ],
+        down: &[
+            "DROP INDEX idx_notes_deleted_at ON notes",
+            "ALTER TABLE notes DROP COLUMN deleted_at",
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "create_note_revisions",
+        up: &[r#"
+CREATE TABLE IF NOT EXISTS note_revisions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    note_id BIGINT NOT NULL,
+    revision INT UNSIGNED NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    content TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_note_revisions_note_revision (note_id, revision),
+    CONSTRAINT fk_note_revisions_note FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE
+)"#],
+        down: &["DROP TABLE IF EXISTS note_revisions"],
+    },
+    Migration {
+        version: 7,
+        name: "add_notes_version",
+        up: &["ALTER TABLE notes ADD COLUMN version INT UNSIGNED NOT NULL DEFAULT 1"],
+        down: &["ALTER TABLE notes DROP COLUMN version"],
+    },
+    Migration {
+        version: 8,
+        name: "notes_timestamps_microseconds",
+        up: &[
+            r#"
+ALTER TABLE notes
+    MODIFY created_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6),
+    MODIFY updated_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6),
+    MODIFY deleted_at TIMESTAMP(6) NULL DEFAULT NULL"#,
+            "ALTER TABLE note_revisions MODIFY created_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6)",
+        ],
+        down: &[
+            "ALTER TABLE note_revisions MODIFY created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP",
+            r#"
+ALTER TABLE notes
+    MODIFY created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    MODIFY updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    MODIFY deleted_at TIMESTAMP NULL DEFAULT NULL"#,
+        ],
+    },
+    Migration {
+        version: 9,
+        name: "create_note_attachments",
+        up: &[r#"
+CREATE TABLE IF NOT EXISTS note_attachments (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    note_id BIGINT NOT NULL,
+    file_name VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(255) NOT NULL,
+    size_bytes BIGINT UNSIGNED NOT NULL,
+    sha256 CHAR(64) NOT NULL,
+    created_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6),
+    KEY idx_note_attachments_note (note_id),
+    KEY idx_note_attachments_sha256 (sha256),
+    CONSTRAINT fk_note_attachments_note FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE
+)"#],
+        down: &["DROP TABLE IF EXISTS note_attachments"],
+    },
+];
+
+const CREATE_SCHEMA_MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_migrations (
+    version INT UNSIGNED PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    checksum CHAR(64) NOT NULL,
+    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+)"#;
+
+// Held while migrating so two app instances never migrate at the same time.
+const MIGRATION_LOCK: &str = "app_schema_migrations";
+const MIGRATION_LOCK_TIMEOUT_SECS: u32 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingMigration {
+    pub version: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub current_version: Option<u32>,
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<PendingMigration>,
+}
+
+pub struct MigrationRunner {
+    pool: Pool,
+}
+
+impl MigrationRunner {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Verifies applied migrations and applies every pending one in order.
+    /// Returns the versions that were applied.
+    pub async fn run_pending(&self) -> Result<Vec<u32>> {
+        let mut conn = self.pool.get_conn().await?;
+        Self::acquire_lock(&mut conn).await?;
+
+        let result = Self::apply_pending(&mut conn).await;
+
+        Self::release_lock(&mut conn).await?;
+        result
+    }
+
+    pub async fn status(&self) -> Result<MigrationStatus> {
+        let mut conn = self.pool.get_conn().await?;
+        conn.query_drop(CREATE_SCHEMA_MIGRATIONS_TABLE).await?;
+
+        let applied = Self::applied_migrations(&mut conn).await?;
+        let pending = MIGRATIONS.iter()
+            .filter(|m| !applied.iter().any(|a| a.version == m.version))
+            .map(|m| PendingMigration {
+                version: m.version,
+                name: m.name.to_string(),
+            })
+            .collect();
+
+        Ok(MigrationStatus {
+            current_version: applied.last().map(|m| m.version),
+            applied,
+            pending,
+        })
+    }
+
+    /// Runs the `down` statements of the most recently applied migration and
+    /// removes its bookkeeping row.
+    pub async fn rollback_last(&self) -> Result<Option<AppliedMigration>> {
+        let mut conn = self.pool.get_conn().await?;
+        Self::acquire_lock(&mut conn).await?;
+
+        let result = Self::rollback(&mut conn).await;
+
+        Self::release_lock(&mut conn).await?;
+        result
+    }
+
+    async fn apply_pending(conn: &mut Conn) -> Result<Vec<u32>> {
+        conn.query_drop(CREATE_SCHEMA_MIGRATIONS_TABLE).await?;
+
+        let applied = Self::applied_migrations(conn).await?;
+        Self::verify_checksums(&applied)?;
+
+        let mut newly_applied = Vec::new();
+        for migration in MIGRATIONS {
+            if applied.iter().any(|a| a.version == migration.version) {
+                continue;
+            }
+
+            log::info!("Applying migration {} ({})", migration.version, migration.name);
+            for statement in migration.up {
+                conn.query_drop(*statement).await
+                    .map_err(|e| anyhow!("Migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+            }
+
+            conn.exec_drop(
+                r"INSERT INTO schema_migrations (version, name, checksum) VALUES (:version, :name, :checksum)",
+                params! {
+                    "version" => migration.version,
+                    "name" => migration.name,
+                    "checksum" => migration.checksum(),
+                },
+            ).await?;
+
+            newly_applied.push(migration.version);
+        }
+
+        Ok(newly_applied)
+    }
+
+    async fn rollback(conn: &mut Conn) -> Result<Option<AppliedMigration>> {
+        conn.query_drop(CREATE_SCHEMA_MIGRATIONS_TABLE).await?;
+
+        let applied = Self::applied_migrations(conn).await?;
+        let last = match applied.last() {
+            Some(last) => last.clone(),
+            None => return Ok(None),
+        };
+
+        let migration = MIGRATIONS.iter()
+            .find(|m| m.version == last.version)
+            .ok_or_else(|| anyhow!("Migration {} is not known to this version of the app", last.version))?;
+
+        if migration.checksum() != last.checksum {
+            return Err(anyhow!("Checksum mismatch for migration {} ({}); refusing to roll back", migration.version, migration.name));
+        }
+
+        log::info!("Rolling back migration {} ({})", migration.version, migration.name);
+        for statement in migration.down {
+            conn.query_drop(*statement).await
+                .map_err(|e| anyhow!("Rollback of migration {} ({}) failed: {}", migration.version, migration.name, e))?;
+        }
+
+        conn.exec_drop(
+            r"DELETE FROM schema_migrations WHERE version = :version",
+            params! { "version" => migration.version },
+        ).await?;
+
+        Ok(Some(last))
+    }
+
+    async fn applied_migrations(conn: &mut Conn) -> Result<Vec<AppliedMigration>> {
+        let rows: Vec<(u32, String, String, Option<String>)> = conn.query(
+            r"SELECT version, name, checksum, DATE_FORMAT(applied_at, '%Y-%m-%d %H:%i:%s')
+              FROM schema_migrations ORDER BY version"
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(|(version, name, checksum, applied_at)| AppliedMigration { version, name, checksum, applied_at })
+            .collect())
+    }
+
+    fn verify_checksums(applied: &[AppliedMigration]) -> Result<()> {
+        for record in applied {
+            let migration = MIGRATIONS.iter()
+                .find(|m| m.version == record.version)
+                .ok_or_else(|| anyhow!("Database has migration {} ({}) which this version of the app does not know; is the app out of date?", record.version, record.name))?;
+
+            if migration.checksum() != record.checksum {
+                return Err(anyhow!("Checksum mismatch for applied migration {} ({}); migrations must not be edited after they ship", record.version, record.name));
+            }
+        }
+        Ok(())
+    }
+
+    async fn acquire_lock(conn: &mut Conn) -> Result<()> {
+        let acquired: Option<Option<i64>> = conn.exec_first(
+            r"SELECT GET_LOCK(:name, :timeout)",
+            params! { "name" => MIGRATION_LOCK, "timeout" => MIGRATION_LOCK_TIMEOUT_SECS },
+        ).await?;
+
+        match acquired.flatten() {
+            Some(1) => Ok(()),
+            _ => Err(anyhow!("Timed out waiting for the schema migration lock")),
+        }
+    }
+
+    async fn release_lock(conn: &mut Conn) -> Result<()> {
+        conn.exec_drop(r"SELECT RELEASE_LOCK(:name)", params! { "name" => MIGRATION_LOCK }).await?;
+        Ok(())
+    }
+}
@@ -1,95 +1,674 @@
 // src/db/notes.rs
 
-use mysql_async::{prelude::*, Pool, Error as MySqlError};
+use std::collections::HashMap;
+use mysql_async::{prelude::*, Pool, Params, Row, Transaction, TxOpts, Value};
 use anyhow::Result;
-use crate::models::Note;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use crate::models::{
+    BatchResult, DeleteResult, Note, NoteListQuery, NotePage, NoteSortField, SearchHit, SearchQuery, SearchResults, SortDirection,
+};
+use crate::error::AppError;
+use crate::events::{NoteEvents, NOTE_CREATED, NOTE_DELETED, NOTE_UPDATED};
+use crate::search;
+use crate::timestamps;
+use super::{revisions, tags};
+
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 20;
+pub(crate) const MAX_PAGE_SIZE: u32 = 100;
+
+/// Position of a note within a sorted listing. Serialized to JSON and
+/// base64-encoded so the frontend can treat it as an opaque token.
+/// Shared with the SQLite store, which stores timestamps in the same RFC 3339
+/// form used for `value`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct NoteCursor {
+    pub sort_by: NoteSortField,
+    pub direction: SortDirection,
+    pub value: String,
+    pub id: i64,
+    /// Set on `prev_cursor`s: read the page before this position.
+    pub backward: bool,
+}
+
+impl NoteCursor {
+    pub fn at(note: &Note, query: &NoteListQuery, backward: bool) -> Option<Self> {
+        let value = match query.sort_by {
+            NoteSortField::CreatedAt => note.created_at?.to_rfc3339_opts(SecondsFormat::Micros, true),
+            NoteSortField::UpdatedAt => note.updated_at?.to_rfc3339_opts(SecondsFormat::Micros, true),
+            NoteSortField::Title => note.title.clone(),
+        };
+
+        Some(Self {
+            sort_by: query.sort_by,
+            direction: query.direction,
+            value,
+            id: note.id?,
+            backward,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(raw: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(raw).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// The cursor position as a query parameter. Timestamps are bound as
+    /// dates so the comparison keeps microseconds.
+    fn sql_value(&self) -> Option<Value> {
+        match self.sort_by {
+            NoteSortField::Title => Some(Value::from(self.value.as_str())),
+            NoteSortField::CreatedAt | NoteSortField::UpdatedAt => DateTime::parse_from_rfc3339(&self.value)
+                .ok()
+                .map(|t| timestamps::to_value(&t.with_timezone(&Utc))),
+        }
+    }
+}
 
 pub struct NoteRepository {
     pool: Pool,
+    events: NoteEvents,
 }
 
 impl NoteRepository {
     pub fn new(pool: Pool) -> Self {
-        Self { pool }
+        Self { pool, events: NoteEvents::default() }
     }
 
-    pub async fn create_note(&self, note: &Note) -> Result<Note, MySqlError> {
-        let mut conn = self.pool.get_conn().await?;
+    /// Publishes every change this repository makes through `events`.
+    pub fn with_events(mut self, events: NoteEvents) -> Self {
+        self.events = events;
+        self
+    }
 
-        let insert_query = r"INSERT INTO notes (title, content) VALUES (:title, :content)";
-        let insert_params = params! {
-            "title" => &note.title,
-            "content" => &note.content
-        };
+    /// Inserts the note and its tags in one transaction, so a failure
+    /// leaves nothing behind for a retry to duplicate.
+    pub async fn create_note(&self, note: &Note) -> Result<Note, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+        let created_note = insert_note(&mut tx, note).await?;
+        tx.commit().await?;
 
-        conn.exec_drop(insert_query, insert_params).await?;
-        
-        let last_id = conn.last_insert_id();
+        self.events.created(&created_note);
+        Ok(created_note)
+    }
 
-        let fetch_query = r"SELECT id, title, content, created_at, updated_at FROM notes WHERE id = :id";
-        let fetch_params = params! {
-            "id" => last_id
-        };
+    pub async fn get_all_notes(&self) -> Result<Vec<Note>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
 
-        let created_note: Note = conn.exec_first(fetch_query, fetch_params)
-            .await?
-            .ok_or(MySqlError::Other("Note not found".into()))?;
+        let query = r"SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes WHERE deleted_at IS NULL ORDER BY created_at DESC";
+        let mut notes: Vec<Note> = conn.exec(query, ()).await?;
+        tags::load_tags(&mut conn, &mut notes).await?;
 
-        Ok(created_note)
+        Ok(notes)
     }
 
-    pub async fn get_all_notes(&self) -> Result<Vec<Note>, MySqlError> {
+    /// Up to `limit` notes not in the trash with an id above `after`, in id
+    /// order. Lets bulk exports walk the table a batch at a time instead of
+    /// loading it whole.
+    pub async fn notes_after(&self, after: i64, limit: usize) -> Result<Vec<Note>, AppError> {
         let mut conn = self.pool.get_conn().await?;
 
-        let query = r"SELECT id, title, content, created_at, updated_at FROM notes ORDER BY created_at DESC";
-        let notes: Vec<Note> = conn.exec(query, ()).await?;
+        let query = r"SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes
+                      WHERE deleted_at IS NULL AND id > :after ORDER BY id LIMIT :limit";
+        let mut notes: Vec<Note> = conn.exec(query, params! { "after" => after, "limit" => limit as u64 }).await?;
+        tags::load_tags(&mut conn, &mut notes).await?;
 
         Ok(notes)
     }
 
-    pub async fn get_note_by_id(&self, id: i64) -> Result<Note, MySqlError> {
+    pub async fn count_notes(&self) -> Result<u64, AppError> {
         let mut conn = self.pool.get_conn().await?;
 
-        let query = r"SELECT id, title, content, created_at, updated_at FROM notes WHERE id = :id";
+        let total: Option<u64> = conn.exec_first(r"SELECT COUNT(*) FROM notes WHERE deleted_at IS NULL", ()).await?;
+        Ok(total.unwrap_or(0))
+    }
+
+    pub async fn get_note_by_id(&self, id: i64) -> Result<Note, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes WHERE id = :id AND deleted_at IS NULL";
         let params = params! {
             "id" => id
         };
 
-        let note: Option<Note> = conn.exec_first(query, params).await?;
+        let mut note: Note = conn.exec_first(query, params)
+            .await?
+            .ok_or_else(|| AppError::not_found("Note"))?;
+        note.tags = tags::tags_for_note(&mut conn, id).await?;
 
-        note.ok_or(MySqlError::Other("Note not found".into()))
+        Ok(note)
     }
 
-    pub async fn update_note(&self, id: i64, note: &Note) -> Result<Note, MySqlError> {
+    /// Fetches a note whether or not it is in the trash. Used by sync, which
+    /// needs to see trashed notes too.
+    pub async fn find_note(&self, id: i64) -> Result<Option<Note>, AppError> {
         let mut conn = self.pool.get_conn().await?;
+        find_note(&mut conn, id).await
+    }
 
-        let update_query = r"UPDATE notes SET title = :title, content = :content WHERE id = :id";
-        let update_params = params! {
-            "title" => &note.title,
-            "content" => &note.content,
-            "id" => id
-        };
+    /// Updates title and content. The previous values are kept as a revision
+    /// in the same transaction.
+    ///
+    /// If `note.version` is set and no longer matches the stored version, the
+    /// update is rejected with `AppError::Conflict`. A note without a
+    /// version is written unconditionally.
+    pub async fn update_note(&self, id: i64, note: &Note) -> Result<Note, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
 
-        conn.exec_drop(update_query, update_params).await?;
+        if let Err(e) = write_update(&mut tx, id, note).await {
+            tx.rollback().await?;
+            return Err(e);
+        }
+        tx.commit().await?;
 
         // Fetch updated note
-        self.get_note_by_id(id).await
+        let updated = self.get_note_by_id(id).await?;
+        self.events.updated(&updated);
+        Ok(updated)
+    }
+
+    /// Creates every note or none of them.
+    pub async fn batch_create_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        let mut ids = Vec::with_capacity(notes.len());
+        for (index, note) in notes.iter().enumerate() {
+            let created = async {
+                tx.exec_drop(
+                    r"INSERT INTO notes (title, content) VALUES (:title, :content)",
+                    params! { "title" => &note.title, "content" => &note.content },
+                ).await?;
+                let id = tx.last_insert_id().unwrap_or_default() as i64;
+                tags::attach_tags(&mut tx, id, &note.tags).await?;
+                Ok::<_, AppError>(id)
+            }.await;
+
+            match created {
+                Ok(id) => ids.push(id),
+                Err(e) => {
+                    tx.rollback().await?;
+                    return Ok(BatchResult::rolled_back(vec![None; notes.len()], index, e));
+                }
+            }
+        }
+        tx.commit().await?;
+
+        let result = self.batch_written(&ids).await?;
+        self.events.batch(NOTE_CREATED, &result);
+        Ok(result)
+    }
+
+    /// Applies every update or none of them. Each update is checked and
+    /// recorded like `update_note`, and also replaces the note's tags with
+    /// `note.tags`, so a batch can retag notes.
+    pub async fn batch_update_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let ids: Vec<Option<i64>> = notes.iter().map(|n| n.id).collect();
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        for (index, note) in notes.iter().enumerate() {
+            let updated = async {
+                let id = note.id.ok_or_else(|| AppError::validation("Note id is required"))?;
+                write_update(&mut tx, id, note).await?;
+                tags::set_tags(&mut tx, id, &note.tags).await?;
+                Ok::<_, AppError>(())
+            }.await;
+
+            if let Err(e) = updated {
+                tx.rollback().await?;
+                return Ok(BatchResult::rolled_back(ids, index, e));
+            }
+        }
+        tx.commit().await?;
+
+        let result = self.batch_written(&ids.into_iter().flatten().collect::<Vec<_>>()).await?;
+        self.events.batch(NOTE_UPDATED, &result);
+        Ok(result)
+    }
+
+    /// Moves every note to the trash or none of them. An id that is missing
+    /// or already trashed fails the batch.
+    pub async fn batch_delete_notes(&self, ids: &[i64]) -> Result<BatchResult, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        for (index, &id) in ids.iter().enumerate() {
+            // `updated_at = updated_at` stops ON UPDATE from bumping the edit time.
+            let trashed = tx.exec_drop(
+                r"UPDATE notes SET deleted_at = CURRENT_TIMESTAMP(6), updated_at = updated_at
+                  WHERE id = :id AND deleted_at IS NULL",
+                params! { "id" => id },
+            ).await;
+
+            let failure = match trashed {
+                Ok(()) if tx.affected_rows() > 0 => None,
+                Ok(()) => Some(AppError::not_found("Note")),
+                Err(e) => Some(e.into()),
+            };
+            if let Some(e) = failure {
+                tx.rollback().await?;
+                return Ok(BatchResult::rolled_back(ids.iter().copied().map(Some).collect(), index, e));
+            }
+        }
+        tx.commit().await?;
+
+        let result = self.batch_written(ids).await?;
+        self.events.batch(NOTE_DELETED, &result);
+        Ok(result)
     }
 
-    pub async fn delete_note(&self, id: i64) -> Result<bool, MySqlError> {
+    /// Result of a committed batch, with each note as stored.
+    async fn batch_written(&self, ids: &[i64]) -> Result<BatchResult, AppError> {
         let mut conn = self.pool.get_conn().await?;
-    
-        let query = r"DELETE FROM notes WHERE id = :id";
+
+        let mut by_id: HashMap<i64, Note> = HashMap::new();
+        if !ids.is_empty() {
+            let (placeholders, params) = tags::named_list("id", ids.iter().map(|&id| Value::from(id)));
+            let query = format!(
+                "SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes WHERE id IN ({})",
+                placeholders
+            );
+            let mut notes: Vec<Note> = conn.exec(query, params).await?;
+            tags::load_tags(&mut conn, &mut notes).await?;
+            by_id = notes.into_iter().filter_map(|n| Some((n.id?, n))).collect();
+        }
+
+        Ok(BatchResult::committed(ids.iter().map(|&id| (id, by_id.remove(&id))).collect()))
+    }
+
+    /// Moves a note to the trash. It can be brought back with `restore_note`
+    /// until it is purged.
+    pub async fn delete_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        // `updated_at = updated_at` stops ON UPDATE from bumping the edit time.
+        let query = r"UPDATE notes SET deleted_at = CURRENT_TIMESTAMP(6), updated_at = updated_at
+                      WHERE id = :id AND deleted_at IS NULL";
         let params = params! {
             "id" => id
         };
-    
+
         conn.exec_drop(query, params).await?;
-    
-        // Use a separate query to check affected rows
-        let count_query = r"SELECT ROW_COUNT() as affected";
-        let affected_rows: Option<i64> = conn.exec_first(count_query, ()).await?;
-    
-        Ok(affected_rows.map_or(false, |rows| rows > 0))
-    }
-}
\ No newline at end of file
+        let rows_affected = conn.affected_rows();
+
+        if rows_affected > 0 {
+            if let Some(note) = find_note(&mut conn, id).await? {
+                self.events.deleted(&note);
+            }
+        }
+        Ok(DeleteResult { id, rows_affected })
+    }
+
+    /// Full-text search over title and content using the
+    /// `ft_notes_title_content` index. Note that InnoDB ignores stopwords and
+    /// words shorter than `innodb_ft_min_token_size` (3 by default).
+    pub async fn search_notes(&self, query: &SearchQuery) -> Result<SearchResults, AppError> {
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let text = query.query.trim();
+
+        if text.is_empty() {
+            return Ok(SearchResults { hits: Vec::new(), total: 0, page, page_size });
+        }
+
+        let mut conn = self.pool.get_conn().await?;
+
+        // The search modifier cannot be bound as a parameter; it comes from
+        // a closed enum so formatting it in is safe.
+        let against = format!("MATCH(title, content) AGAINST (:query {})", query.mode.as_sql());
+
+        let count_query = format!("SELECT COUNT(*) FROM notes WHERE deleted_at IS NULL AND {}", against);
+        let total: Option<u64> = conn.exec_first(count_query, params! { "query" => text }).await?;
+
+        let search_query = format!(
+            "SELECT id, title, content, created_at, updated_at, deleted_at, version, {against} AS score \
+             FROM notes WHERE deleted_at IS NULL AND {against} \
+             ORDER BY score DESC, id DESC LIMIT :limit OFFSET :offset"
+        );
+        let rows: Vec<Row> = conn.exec(search_query, params! {
+            "query" => text,
+            "limit" => page_size,
+            "offset" => u64::from(page - 1) * u64::from(page_size),
+        }).await?;
+
+        let (mut notes, scores): (Vec<Note>, Vec<f64>) = rows.into_iter()
+            .map(|row| {
+                let score: f64 = row.get(7).unwrap_or(0.0);
+                (Note::from_row(row), score)
+            })
+            .unzip();
+        tags::load_tags(&mut conn, &mut notes).await?;
+
+        let terms = search::search_terms(text, query.mode);
+        let hits = notes.into_iter()
+            .zip(scores)
+            .map(|(note, score)| {
+                SearchHit {
+                    title_highlight: search::highlight(&note.title, &terms),
+                    snippet: search::snippet(note.content.as_deref().unwrap_or_default(), &terms),
+                    score,
+                    note,
+                }
+            })
+            .collect();
+
+        Ok(SearchResults {
+            hits,
+            total: total.unwrap_or(0),
+            page,
+            page_size,
+        })
+    }
+
+    /// Keyset-paginated listing. Ties on the sort column are broken by id so
+    /// every note appears exactly once while paging in either direction.
+    pub async fn list_notes(&self, query: &NoteListQuery) -> Result<NotePage, AppError> {
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        let cursor = match &query.cursor {
+            Some(raw) => Some(
+                NoteCursor::decode(raw)
+                    .filter(|c| c.sort_by == query.sort_by && c.direction == query.direction)
+                    .filter(|c| c.sql_value().is_some())
+                    .ok_or_else(|| AppError::validation("Invalid or mismatched cursor"))?
+            ),
+            None => None,
+        };
+
+        let mut filters: Vec<String> = vec!["deleted_at IS NULL".to_string()];
+        let mut params: HashMap<Vec<u8>, Value> = HashMap::new();
+        let ranges = [
+            ("created_after", "created_at >=", &query.created_after),
+            ("created_before", "created_at <", &query.created_before),
+            ("updated_after", "updated_at >=", &query.updated_after),
+            ("updated_before", "updated_at <", &query.updated_before),
+        ];
+        for (name, condition, value) in ranges {
+            if let Some(value) = value {
+                filters.push(format!("{} :{}", condition, name));
+                params.insert(name.as_bytes().to_vec(), timestamps::to_value(value));
+            }
+        }
+
+        let mut conn = self.pool.get_conn().await?;
+
+        let count_query = format!("SELECT COUNT(*) FROM notes {}", where_clause(&filters));
+        let total: Option<u64> = conn.exec_first(count_query, Params::Named(params.clone())).await?;
+
+        // Reading backwards flips the scan direction; rows are reversed
+        // afterwards so the page is always in the requested order.
+        let backward = cursor.as_ref().is_some_and(|c| c.backward);
+        let descending = (query.direction == SortDirection::Desc) != backward;
+        let (cmp, order) = if descending { ("<", "DESC") } else { (">", "ASC") };
+        let column = query.sort_by.column();
+
+        if let Some(cursor) = &cursor {
+            filters.push(format!(
+                "({column} {cmp} :cursor_value OR ({column} = :cursor_value AND id {cmp} :cursor_id))"
+            ));
+            params.insert(b"cursor_value".to_vec(), cursor.sql_value().unwrap_or(Value::NULL));
+            params.insert(b"cursor_id".to_vec(), Value::from(cursor.id));
+        }
+        params.insert(b"limit".to_vec(), Value::from(page_size + 1));
+
+        let page_query = format!(
+            "SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes {} \
+             ORDER BY {column} {order}, id {order} LIMIT :limit",
+            where_clause(&filters)
+        );
+        let mut notes: Vec<Note> = conn.exec(page_query, Params::Named(params)).await?;
+        tags::load_tags(&mut conn, &mut notes).await?;
+
+        let has_more = notes.len() > page_size as usize;
+        notes.truncate(page_size as usize);
+        if backward {
+            notes.reverse();
+        }
+
+        let (has_next, has_prev) = if backward {
+            (true, has_more)
+        } else {
+            (has_more, cursor.is_some())
+        };
+
+        let next_cursor = notes.last()
+            .filter(|_| has_next)
+            .and_then(|n| NoteCursor::at(n, query, false))
+            .map(|c| c.encode());
+        let prev_cursor = notes.first()
+            .filter(|_| has_prev)
+            .and_then(|n| NoteCursor::at(n, query, true))
+            .map(|c| c.encode());
+
+        Ok(NotePage {
+            notes,
+            next_cursor,
+            prev_cursor,
+            total: total.unwrap_or(0),
+        })
+    }
+
+    /// Notes carrying any (or, with `match_all`, every) of the given tags,
+    /// newest first.
+    pub async fn get_notes_by_tags(&self, tag_names: &[String], match_all: bool) -> Result<Vec<Note>, AppError> {
+        let tag_names = tags::normalize_tags(tag_names);
+        if tag_names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.pool.get_conn().await?;
+
+        let required = tag_names.len();
+        let (placeholders, params) = tags::named_list("tag", tag_names.into_iter().map(Value::from));
+        let having = if match_all {
+            format!("HAVING COUNT(DISTINCT nt.tag_id) = {}", required)
+        } else {
+            String::new()
+        };
+        let query = format!(
+            "SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes \
+             WHERE deleted_at IS NULL AND id IN ( \
+                 SELECT nt.note_id FROM note_tags nt \
+                 JOIN tags t ON t.id = nt.tag_id \
+                 WHERE t.name IN ({}) \
+                 GROUP BY nt.note_id \
+                 {} \
+             ) \
+             ORDER BY created_at DESC",
+            placeholders, having
+        );
+
+        let mut notes: Vec<Note> = conn.exec(query, params).await?;
+        tags::load_tags(&mut conn, &mut notes).await?;
+
+        Ok(notes)
+    }
+
+    pub async fn list_trash(&self) -> Result<Vec<Note>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes
+                      WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC";
+        let mut notes: Vec<Note> = conn.exec(query, ()).await?;
+        tags::load_tags(&mut conn, &mut notes).await?;
+
+        Ok(notes)
+    }
+
+    pub async fn restore_note(&self, id: i64) -> Result<Note, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"UPDATE notes SET deleted_at = NULL, updated_at = updated_at
+                      WHERE id = :id AND deleted_at IS NOT NULL";
+        conn.exec_drop(query, params! { "id" => id }).await?;
+
+        if conn.affected_rows() == 0 {
+            return Err(AppError::NotFound("Note not found in trash".into()));
+        }
+
+        let restored = self.get_note_by_id(id).await?;
+        self.events.created(&restored);
+        Ok(restored)
+    }
+
+    /// Permanently deletes a note. Only notes already in the trash can be purged.
+    pub async fn purge_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"DELETE FROM notes WHERE id = :id AND deleted_at IS NOT NULL";
+        conn.exec_drop(query, params! { "id" => id }).await?;
+
+        Ok(DeleteResult { id, rows_affected: conn.affected_rows() })
+    }
+
+    /// Permanently deletes notes that have been in the trash longer than
+    /// `retention_days`. Returns the number of notes removed.
+    pub async fn purge_expired_trash(&self, retention_days: u32) -> Result<u64, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"DELETE FROM notes
+                      WHERE deleted_at IS NOT NULL
+                        AND deleted_at < CURRENT_TIMESTAMP - INTERVAL :days DAY";
+        conn.exec_drop(query, params! { "days" => retention_days }).await?;
+
+        Ok(conn.affected_rows())
+    }
+}
+
+async fn find_note<Q: Queryable>(conn: &mut Q, id: i64) -> Result<Option<Note>, AppError> {
+    let query = r"SELECT id, title, content, created_at, updated_at, deleted_at, version FROM notes WHERE id = :id";
+    let note: Option<Note> = conn.exec_first(query, params! { "id" => id }).await?;
+
+    match note {
+        Some(mut note) => {
+            note.tags = tags::tags_for_note(conn, id).await?;
+            Ok(Some(note))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Inserts a note and its tags inside `tx` and returns it as stored.
+pub(crate) async fn insert_note(tx: &mut Transaction<'_>, note: &Note) -> Result<Note, AppError> {
+    tx.exec_drop(
+        r"INSERT INTO notes (title, content) VALUES (:title, :content)",
+        params! { "title" => &note.title, "content" => &note.content },
+    ).await?;
+    let id = tx.last_insert_id().unwrap_or_default() as i64;
+    tags::attach_tags(tx, id, &note.tags).await?;
+
+    find_note(tx, id).await?.ok_or_else(|| AppError::not_found("Note"))
+}
+
+/// Writes title and content inside `tx`, keeping the previous values as a
+/// revision. A stale `note.version` is rejected with `AppError::Conflict`
+/// carrying the note as currently stored; the caller rolls back.
+async fn write_update(tx: &mut Transaction<'_>, id: i64, note: &Note) -> Result<(), AppError> {
+    let current_query = r"SELECT title, content, version FROM notes WHERE id = :id AND deleted_at IS NULL FOR UPDATE";
+    let (title, content, version): (String, Option<String>, u32) = tx.exec_first(current_query, params! { "id" => id })
+        .await?
+        .ok_or_else(|| AppError::not_found("Note"))?;
+
+    if note.version.is_some_and(|expected| expected != version) {
+        let current = find_note(tx, id).await?;
+        return Err(AppError::Conflict {
+            message: format!("Note was modified elsewhere (now at version {})", version),
+            current: current.map(Box::new),
+        });
+    }
+
+    revisions::record_revision(tx, id, &title, content.as_deref()).await?;
+
+    let update_query = r"UPDATE notes SET title = :title, content = :content, version = version + 1
+                         WHERE id = :id AND deleted_at IS NULL";
+    let update_params = params! {
+        "title" => &note.title,
+        "content" => &note.content,
+        "id" => id
+    };
+
+    tx.exec_drop(update_query, update_params).await?;
+
+    // `version` always changes, so a matched row is always counted.
+    if tx.affected_rows() == 0 {
+        return Err(AppError::not_found("Note"));
+    }
+    Ok(())
+}
+
+fn where_clause(filters: &[String]) -> String {
+    if filters.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", filters.join(" AND "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn note() -> Note {
+        let created = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+            + chrono::Duration::microseconds(123_456);
+        Note {
+            id: Some(42),
+            title: "Über \"quotes\"".into(),
+            content: None,
+            created_at: Some(created),
+            updated_at: None,
+            deleted_at: None,
+            version: Some(1),
+            tags: Vec::new(),
+        }
+    }
+
+    fn query(sort_by: NoteSortField) -> NoteListQuery {
+        NoteListQuery { sort_by, direction: SortDirection::Asc, ..Default::default() }
+    }
+
+    #[test]
+    fn cursors_round_trip_through_their_encoding() {
+        let cursor = NoteCursor::at(&note(), &query(NoteSortField::CreatedAt), true).unwrap();
+        let raw = cursor.encode();
+        assert!(raw.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let decoded = NoteCursor::decode(&raw).unwrap();
+        assert_eq!(decoded.sort_by, NoteSortField::CreatedAt);
+        assert_eq!(decoded.direction, SortDirection::Asc);
+        assert_eq!(decoded.value, "2024-03-01T12:00:00.123456Z");
+        assert_eq!(decoded.id, 42);
+        assert!(decoded.backward);
+    }
+
+    #[test]
+    fn timestamp_cursors_keep_microseconds() {
+        let cursor = NoteCursor::at(&note(), &query(NoteSortField::CreatedAt), false).unwrap();
+        let expected = timestamps::to_value(&note().created_at.unwrap());
+        assert_eq!(cursor.sql_value(), Some(expected));
+
+        let title = NoteCursor::at(&note(), &query(NoteSortField::Title), false).unwrap();
+        assert_eq!(title.sql_value(), Some(Value::from("Über \"quotes\"")));
+    }
+
+    #[test]
+    fn notes_without_the_sort_value_have_no_cursor() {
+        assert!(NoteCursor::at(&note(), &query(NoteSortField::UpdatedAt), false).is_none());
+        let unsaved = Note { id: None, ..note() };
+        assert!(NoteCursor::at(&unsaved, &query(NoteSortField::Title), false).is_none());
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert!(NoteCursor::decode("not base64!").is_none());
+        assert!(NoteCursor::decode(&URL_SAFE_NO_PAD.encode(b"{\"id\":1}")).is_none());
+
+        let mut cursor = NoteCursor::at(&note(), &query(NoteSortField::CreatedAt), false).unwrap();
+        cursor.value = "yesterday".into();
+        assert!(NoteCursor::decode(&cursor.encode()).unwrap().sql_value().is_none());
+    }
+}
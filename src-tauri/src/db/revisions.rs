@@ -0,0 +1,125 @@
+// src/db/revisions.rs
+
+use mysql_async::{prelude::*, Pool, Error as MySqlError};
+use anyhow::Result;
+use similar::{ChangeTag, TextDiff};
+use crate::models::{DiffKind, DiffLine, Note, NoteRevision, RevisionDiff};
+use crate::error::AppError;
+use super::notes::NoteRepository;
+
+pub struct RevisionRepository {
+    pool: Pool,
+}
+
+impl RevisionRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Revisions of a note, newest first.
+    pub async fn list_revisions(&self, note_id: i64) -> Result<Vec<NoteRevision>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, note_id, revision, title, content, created_at
+                      FROM note_revisions WHERE note_id = :note_id
+                      ORDER BY revision DESC";
+        Ok(conn.exec(query, params! { "note_id" => note_id }).await?)
+    }
+
+    pub async fn get_revision(&self, note_id: i64, revision: u32) -> Result<NoteRevision, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        let query = r"SELECT id, note_id, revision, title, content, created_at
+                      FROM note_revisions WHERE note_id = :note_id AND revision = :revision";
+        let found: Option<NoteRevision> = conn.exec_first(query, params! {
+            "note_id" => note_id,
+            "revision" => revision,
+        }).await?;
+
+        found.ok_or_else(|| AppError::not_found("Revision"))
+    }
+
+    /// Line-level diff of the content between two revisions. When `to` is
+    /// `None` the note's current content is used as the newer side.
+    pub async fn diff_revisions(&self, note_id: i64, from: u32, to: Option<u32>) -> Result<RevisionDiff, AppError> {
+        let old = self.get_revision(note_id, from).await?;
+        let (new_title, new_content) = match to {
+            Some(to) => {
+                let new = self.get_revision(note_id, to).await?;
+                (new.title, new.content)
+            },
+            None => {
+                let current = NoteRepository::new(self.pool.clone()).get_note_by_id(note_id).await?;
+                (current.title, current.content)
+            }
+        };
+
+        Ok(RevisionDiff {
+            from_revision: from,
+            to_revision: to,
+            lines: diff_lines(
+                old.content.as_deref().unwrap_or_default(),
+                new_content.as_deref().unwrap_or_default(),
+            ),
+            old_title: old.title,
+            new_title,
+        })
+    }
+
+    /// Puts a revision's title and content back on the note. This is a
+    /// normal update, so the content being replaced becomes a new revision.
+    pub async fn restore_revision(&self, note_id: i64, revision: u32) -> Result<Note, AppError> {
+        let target = self.get_revision(note_id, revision).await?;
+        let notes = NoteRepository::new(self.pool.clone());
+
+        let mut note = notes.get_note_by_id(note_id).await?;
+        note.title = target.title;
+        note.content = target.content;
+
+        notes.update_note(note_id, &note).await
+    }
+}
+
+/// Stores the note's current title and content as the next revision. Must be
+/// called inside the transaction that updates the note.
+pub async fn record_revision<Q: Queryable>(
+    conn: &mut Q,
+    note_id: i64,
+    title: &str,
+    content: Option<&str>,
+) -> Result<u32, MySqlError> {
+    let next: Option<u32> = conn.exec_first(
+        r"SELECT COALESCE(MAX(revision), 0) + 1 FROM note_revisions WHERE note_id = :note_id",
+        params! { "note_id" => note_id },
+    ).await?;
+    let revision = next.unwrap_or(1);
+
+    conn.exec_drop(
+        r"INSERT INTO note_revisions (note_id, revision, title, content)
+          VALUES (:note_id, :revision, :title, :content)",
+        params! {
+            "note_id" => note_id,
+            "revision" => revision,
+            "title" => title,
+            "content" => content,
+        },
+    ).await?;
+
+    Ok(revision)
+}
+
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            kind: match change.tag() {
+                ChangeTag::Equal => DiffKind::Equal,
+                ChangeTag::Insert => DiffKind::Insert,
+                ChangeTag::Delete => DiffKind::Delete,
+            },
+            old_line: change.old_index().map(|i| i + 1),
+            new_line: change.new_index().map(|i| i + 1),
+            text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+        })
+        .collect()
+}
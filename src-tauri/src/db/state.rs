@@ -0,0 +1,240 @@
+// src/db/state.rs
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use mysql_async::{prelude::*, Opts, OptsBuilder, Pool};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use crate::error::AppError;
+use crate::events::NoteEvents;
+use crate::store::{MySqlStore, NoteStore, SqliteStore};
+
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Limit on a lazy reconnect, which builds a pool and runs migrations.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Wait after a failed lazy reconnect, doubled on every further failure.
+const RETRY_DELAY_MIN: Duration = Duration::from_secs(5);
+const RETRY_DELAY_MAX: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DatabaseStatus {
+    AwaitingSetup,
+    Ready,
+    Unavailable { reason: String },
+}
+
+/// Connection settings that can be changed at runtime. Fields left as
+/// `None` keep their current value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DatabaseConfigUpdate {
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub port: Option<u16>,
+}
+
+#[derive(Default)]
+struct Inner {
+    opts: Option<Opts>,
+    pool: Option<Pool>,
+    setup_complete: bool,
+    last_error: Option<String>,
+    /// No lazy reconnect is tried before this.
+    retry_at: Option<Instant>,
+    retry_delay: Duration,
+}
+
+/// Owns the MySQL pool for the lifetime of the app.
+///
+/// The pool does not exist until system setup has finished, so commands ask
+/// this state for a pool instead of taking `State<'_, Pool>` directly. Note
+/// commands use `store`, which falls back to the embedded SQLite database
+/// while MySQL is unavailable.
+pub struct DatabaseState {
+    inner: RwLock<Inner>,
+    // Serializes connection attempts so a lazy retry and a reconfigure
+    // cannot build two pools at once.
+    connect_lock: Mutex<()>,
+    offline: SqliteStore,
+    events: NoteEvents,
+}
+
+impl DatabaseState {
+    /// `setup_complete` comes from the saved setup state, so a restart
+    /// reconnects to MySQL without running setup again.
+    pub fn new(offline: SqliteStore, events: NoteEvents, setup_complete: bool) -> Self {
+        Self {
+            inner: RwLock::new(Inner { setup_complete, ..Inner::default() }),
+            connect_lock: Mutex::default(),
+            offline: offline.with_events(events.clone()),
+            events,
+        }
+    }
+
+    /// Marks setup as complete and builds the pool from `DATABASE_URL`.
+    pub async fn initialize(&self) -> Result<()> {
+        self.inner.write().await.setup_complete = true;
+
+        let opts = super::database_opts()?;
+        self.connect(opts).await
+    }
+
+    /// Rebuilds the pool with new credentials or port without restarting the app.
+    pub async fn reconfigure(&self, update: DatabaseConfigUpdate) -> Result<()> {
+        let base = match self.inner.read().await.opts.clone() {
+            Some(opts) => opts,
+            None => super::database_opts()?,
+        };
+
+        let mut builder = OptsBuilder::from_opts(base);
+        if let Some(user) = update.user {
+            builder = builder.user(Some(user));
+        }
+        if let Some(password) = update.password {
+            builder = builder.pass(Some(password));
+        }
+        if let Some(port) = update.port {
+            builder = builder.tcp_port(port);
+        }
+
+        self.connect(builder.into()).await
+    }
+
+    /// Returns the current pool, or `SetupRequired` / `DatabaseUnavailable`
+    /// describing why the database cannot be used yet.
+    pub async fn pool(&self) -> Result<Pool, AppError> {
+        {
+            let inner = self.inner.read().await;
+            if let Some(pool) = &inner.pool {
+                return Ok(pool.clone());
+            }
+            if !inner.setup_complete {
+                return Err(AppError::SetupRequired("System setup has not completed".into()));
+            }
+            if inner.retry_at.is_some_and(|at| Instant::now() < at) {
+                let reason = inner.last_error.as_deref().unwrap_or("Not connected");
+                return Err(AppError::DatabaseUnavailable(format!("Database unavailable: {}", reason)));
+            }
+        }
+
+        // Setup finished but the last connection attempt failed, so retry
+        // lazily on first use, backing off while MySQL stays down so note
+        // commands fall back to SQLite without waiting on it.
+        let unavailable = |e: anyhow::Error| AppError::DatabaseUnavailable(format!("Database unavailable: {}", e));
+        let opts = match self.inner.read().await.opts.clone() {
+            Some(opts) => opts,
+            None => super::database_opts().map_err(unavailable)?,
+        };
+        let attempt = match tokio::time::timeout(CONNECT_TIMEOUT, self.connect(opts)).await {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("Timed out connecting after {}s", CONNECT_TIMEOUT.as_secs())),
+        };
+        if let Err(e) = attempt {
+            self.back_off(&e).await;
+            return Err(unavailable(e));
+        }
+
+        self.inner.read().await.pool.clone()
+            .ok_or_else(|| AppError::DatabaseUnavailable("Database unavailable".into()))
+    }
+
+    /// The store note commands should use: MySQL when it is reachable,
+    /// otherwise the embedded SQLite database, whose writes are synced to
+    /// MySQL later by `db::sync`.
+    pub async fn store(&self) -> Arc<dyn NoteStore> {
+        match self.reachable_pool().await {
+            Some(pool) => Arc::new(MySqlStore::new(pool, self.offline.clone(), self.events.clone())),
+            None => Arc::new(self.offline.clone()),
+        }
+    }
+
+    /// The pool, if the server answers a ping in time. A pool can exist
+    /// while the container behind it is stopped.
+    pub async fn reachable_pool(&self) -> Option<Pool> {
+        let pool = self.pool().await.ok()?;
+        let ping = async {
+            let mut conn = pool.get_conn().await?;
+            conn.ping().await
+        };
+
+        match tokio::time::timeout(PING_TIMEOUT, ping).await {
+            Ok(Ok(())) => Some(pool),
+            _ => None,
+        }
+    }
+
+    /// Settings of the current pool, or the configured defaults before the
+    /// first connection.
+    pub async fn opts(&self) -> Result<Opts, AppError> {
+        match self.inner.read().await.opts.clone() {
+            Some(opts) => Ok(opts),
+            None => Ok(super::database_opts()?),
+        }
+    }
+
+    /// Publishes note changes made outside the stores, such as restoring
+    /// a revision.
+    pub fn events(&self) -> &NoteEvents {
+        &self.events
+    }
+
+    pub fn offline_store(&self) -> SqliteStore {
+        self.offline.clone()
+    }
+
+    pub async fn status(&self) -> DatabaseStatus {
+        let inner = self.inner.read().await;
+        if inner.pool.is_some() {
+            DatabaseStatus::Ready
+        } else if !inner.setup_complete {
+            DatabaseStatus::AwaitingSetup
+        } else {
+            DatabaseStatus::Unavailable {
+                reason: inner.last_error.clone().unwrap_or_else(|| "Not connected".to_string()),
+            }
+        }
+    }
+
+    async fn back_off(&self, error: &anyhow::Error) {
+        let mut inner = self.inner.write().await;
+        inner.retry_delay = match inner.retry_at {
+            Some(_) => (inner.retry_delay * 2).min(RETRY_DELAY_MAX),
+            None => RETRY_DELAY_MIN,
+        };
+        inner.retry_at = Some(Instant::now() + inner.retry_delay);
+        inner.last_error = Some(error.to_string());
+    }
+
+    async fn connect(&self, opts: Opts) -> Result<()> {
+        let _guard = self.connect_lock.lock().await;
+
+        let result = super::create_database_pool(opts.clone()).await;
+
+        let mut inner = self.inner.write().await;
+        match result {
+            Ok(pool) => {
+                inner.opts = Some(opts);
+                inner.last_error = None;
+                inner.retry_at = None;
+                if let Some(old) = inner.pool.replace(pool) {
+                    // Let in-flight queries on the old pool finish before it closes.
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = old.disconnect().await {
+                            log::warn!("Failed to disconnect previous pool: {}", e);
+                        }
+                    });
+                }
+                Ok(())
+            },
+            Err(e) => {
+                // A working pool stays in place when a reconfigure fails;
+                // otherwise remember the settings for the next lazy retry.
+                if inner.pool.is_none() {
+                    inner.opts = Some(opts);
+                }
+                inner.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+}
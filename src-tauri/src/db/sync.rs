@@ -0,0 +1,571 @@
+// src/db/sync.rs
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mysql_async::{prelude::*, Pool, Transaction, TxOpts};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use tokio::sync::Mutex;
+use crate::error::AppError;
+use crate::models::Note;
+use crate::store::sqlite::{OutboxEntry, OutboxOp};
+use crate::store::SqliteStore;
+use crate::timestamps::{self, rfc3339_option};
+use super::{revisions, DatabaseState, notes::{self, NoteRepository}, tags::{self, TagRepository}};
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum SyncPhase {
+    Idle,
+    Syncing,
+    /// MySQL is not reachable; writes are queued locally.
+    Offline,
+    Error,
+}
+
+/// Payload of the `sync-status` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub phase: SyncPhase,
+    /// Offline writes not yet pushed to MySQL.
+    pub pending: u64,
+    /// Pending writes MySQL rejected, e.g. as invalid. They are kept and
+    /// retried on every sync.
+    pub failed: u64,
+    /// Conflicts resolved during the last run. The losing side of each is
+    /// kept as a revision of the note.
+    pub conflicts: u32,
+    #[serde(with = "rfc3339_option")]
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+pub struct SyncState {
+    status: std::sync::Mutex<SyncStatus>,
+    // Only one sync runs at a time.
+    running: Mutex<()>,
+}
+
+impl SyncState {
+    pub fn new() -> Self {
+        Self {
+            status: std::sync::Mutex::new(SyncStatus {
+                phase: SyncPhase::Offline,
+                pending: 0,
+                failed: 0,
+                conflicts: 0,
+                last_synced_at: None,
+                last_error: None,
+            }),
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.status.lock().map(|s| s.clone()).unwrap_or_else(|e| e.into_inner().clone())
+    }
+
+    fn update(&self, app: &tauri::AppHandle, f: impl FnOnce(&mut SyncStatus)) -> SyncStatus {
+        let status = {
+            let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut status);
+            status.clone()
+        };
+        if let Err(e) = app.emit("sync-status", status.clone()) {
+            log::warn!("Failed to emit sync status: {}", e);
+        }
+        status
+    }
+}
+
+/// Pushes queued offline writes to MySQL, then refreshes the local mirror.
+/// Does nothing but report `Offline` while MySQL is unreachable.
+pub async fn sync_now(app: &tauri::AppHandle) -> SyncStatus {
+    let state = app.state::<SyncState>();
+    let _running = state.running.lock().await;
+
+    let db = app.state::<DatabaseState>();
+    let cache = db.offline_store();
+    let pending = cache.pending_count().await.unwrap_or(0);
+    let failed = cache.failed_count().await.unwrap_or(0);
+
+    let Some(pool) = db.reachable_pool().await else {
+        return state.update(app, |s| {
+            s.phase = SyncPhase::Offline;
+            s.pending = pending;
+            s.failed = failed;
+        });
+    };
+
+    state.update(app, |s| {
+        s.phase = SyncPhase::Syncing;
+        s.pending = pending;
+    });
+
+    let ctx = SyncContext {
+        notes: NoteRepository::new(pool.clone()),
+        tags: TagRepository::new(pool.clone()),
+        pool,
+        cache,
+    };
+    let result = match ctx.replay().await {
+        Ok(conflicts) => ctx.pull().await.map(|changed| (conflicts, changed)),
+        Err(e) => Err(e),
+    };
+    let before = pending;
+    let pending = ctx.cache.pending_count().await.unwrap_or(pending);
+    let failed = ctx.cache.failed_count().await.unwrap_or(failed);
+
+    match result {
+        Ok((conflicts, changed)) => {
+            // Pushed notes have swapped their local ids for MySQL ones.
+            if changed || pending < before {
+                db.events().reloaded();
+            }
+            state.update(app, |s| {
+                s.phase = SyncPhase::Idle;
+                s.pending = pending;
+                s.failed = failed;
+                s.conflicts = conflicts;
+                s.last_synced_at = Some(Utc::now());
+                s.last_error = None;
+            })
+        },
+        Err(e) => {
+            log::warn!("Sync failed: {}", e);
+            state.update(app, |s| {
+                s.phase = SyncPhase::Error;
+                s.pending = pending;
+                s.failed = failed;
+                s.last_error = Some(e.to_string());
+            })
+        }
+    }
+}
+
+/// Retries sync periodically so edits made while the container was stopped
+/// reach MySQL once it is back. Runs for the lifetime of the app.
+pub async fn run_sync_loop(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(SYNC_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        sync_now(&app).await;
+    }
+}
+
+/// Replays the outbox in order through `apply`. Returns the number of
+/// conflicts resolved. A create or edit MySQL rejects as invalid or missing
+/// is kept and marked failed so the note is not lost; other entries that
+/// can never apply are dropped. Any other error stops the run and leaves
+/// the remaining entries, that one included, for the next sync.
+async fn replay_outbox<F, Fut>(cache: &SqliteStore, mut apply: F) -> Result<u32, AppError>
+where
+    F: FnMut(OutboxEntry) -> Fut,
+    Fut: Future<Output = Result<bool, AppError>>,
+{
+    let mut conflicts = 0;
+    // Local ids already replaced by a MySQL id during this run.
+    let mut pushed: HashSet<i64> = HashSet::new();
+
+    for entry in cache.pending_ops().await? {
+        if pushed.contains(&entry.note_id) {
+            continue;
+        }
+
+        match apply(entry.clone()).await {
+            Ok(conflicted) => conflicts += u32::from(conflicted),
+            Err(e @ (AppError::NotFound(_) | AppError::Validation { .. }))
+                if matches!(entry.op, OutboxOp::Create | OutboxOp::Update) =>
+            {
+                log::warn!("Keeping outbox entry {} ({:?} of note {}): {}", entry.id, entry.op, entry.note_id, e);
+                cache.mark_failed(entry.id, e.to_string()).await?;
+                continue;
+            },
+            Err(e @ (AppError::NotFound(_) | AppError::Validation { .. })) => log::warn!(
+                "Dropping outbox entry {} ({:?} of note {}): {}",
+                entry.id, entry.op, entry.note_id, e
+            ),
+            Err(e) => return Err(e),
+        }
+
+        if entry.op == OutboxOp::Create {
+            pushed.insert(entry.note_id);
+        }
+        cache.ack(entry.id).await?;
+    }
+
+    Ok(conflicts)
+}
+
+/// Where `push_new` creates notes: MySQL, or a fake in tests.
+#[async_trait]
+trait NoteTarget: Sync {
+    /// A created note whose write has not been committed yet.
+    type Pending: Send;
+
+    /// Writes `note`, trashed if it is, and returns its new id.
+    async fn insert(&self, note: &Note) -> Result<(i64, Self::Pending), AppError>;
+    async fn commit(&self, pending: Self::Pending) -> Result<(), AppError>;
+    async fn find(&self, id: i64) -> Result<Option<Note>, AppError>;
+}
+
+/// Creates a local-only note remotely and swaps the cached row over to the
+/// new id. The id is recorded on `entry` before the insert commits, so a
+/// retry after a failure further on finds the note instead of creating it
+/// a second time.
+async fn push_new<T: NoteTarget>(target: &T, cache: &SqliteStore, entry: &OutboxEntry, local: &Note) -> Result<(), AppError> {
+    let existing = match entry.remote_id {
+        Some(id) => target.find(id).await?.and(Some(id)),
+        None => None,
+    };
+    let remote_id = match existing {
+        Some(id) => id,
+        None => {
+            let (id, pending) = target.insert(local).await?;
+            // Dropping `pending` on an error rolls the insert back.
+            cache.record_remote_id(entry.id, id).await?;
+            target.commit(pending).await?;
+            id
+        }
+    };
+
+    if let Some(local_id) = local.id {
+        cache.ack_note(local_id).await?;
+        cache.forget_note(local_id).await?;
+    }
+    if let Some(remote) = target.find(remote_id).await? {
+        cache.mirror_note(&remote).await?;
+    }
+    Ok(())
+}
+
+struct SyncContext {
+    pool: Pool,
+    notes: NoteRepository,
+    tags: TagRepository,
+    cache: SqliteStore,
+}
+
+impl SyncContext {
+    async fn replay(&self) -> Result<u32, AppError> {
+        replay_outbox(&self.cache, |entry| async move { self.apply(&entry).await }).await
+    }
+
+    /// Applies one entry. Returns whether it ran into a conflict.
+    async fn apply(&self, entry: &OutboxEntry) -> Result<bool, AppError> {
+        let id = entry.note_id;
+
+        match entry.op {
+            OutboxOp::Create => {
+                if let Some(local) = self.cache.find_note(id).await? {
+                    push_new(self, &self.cache, entry, &local).await?;
+                }
+                Ok(false)
+            },
+            OutboxOp::Update => {
+                let Some(local) = self.cache.find_note(id).await? else {
+                    return Ok(false);
+                };
+                match self.notes.find_note(id).await? {
+                    Some(remote) => self.push_update(&local, remote, entry.base_version).await,
+                    None => {
+                        // Purged in MySQL while edited here: keep the edit
+                        // as a new note rather than losing it.
+                        push_new(self, &self.cache, entry, &local).await?;
+                        Ok(true)
+                    }
+                }
+            },
+            OutboxOp::Tags => {
+                let (Some(local), Some(remote)) = (self.cache.find_note(id).await?, self.notes.find_note(id).await?) else {
+                    return Ok(false);
+                };
+                let add = tags::missing_tags(&local.tags, &remote.tags);
+                let remove = tags::missing_tags(&remote.tags, &local.tags);
+                if !add.is_empty() {
+                    self.tags.add_tags(id, &add).await?;
+                }
+                if !remove.is_empty() {
+                    self.tags.remove_tags(id, &remove).await?;
+                }
+                Ok(false)
+            },
+            OutboxOp::Delete => {
+                self.notes.delete_note(id).await?;
+                Ok(false)
+            },
+            OutboxOp::Restore => match self.notes.restore_note(id).await {
+                Ok(_) | Err(AppError::NotFound(_)) => Ok(false),
+                Err(e) => Err(e),
+            },
+            OutboxOp::Purge => {
+                self.notes.purge_note(id).await?;
+                Ok(false)
+            },
+        }
+    }
+
+    /// Pushes an offline edit. If MySQL's copy has moved on since the edit
+    /// started, the conflict is resolved by `resolve`.
+    async fn push_update(&self, local: &Note, remote: Note, base_version: Option<u32>) -> Result<bool, AppError> {
+        if remote.deleted_at.is_none() && base_version.is_some() && remote.version == base_version {
+            let edit = Note { version: base_version, ..local.clone() };
+            match self.notes.update_note(local.id.unwrap_or_default(), &edit).await {
+                Ok(_) => return Ok(false),
+                Err(AppError::Conflict { current: Some(current), .. }) => {
+                    self.resolve(local, *current).await?;
+                    return Ok(true);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.resolve(local, remote).await?;
+        Ok(true)
+    }
+
+    /// Last writer wins by `updated_at`. The losing copy is not discarded:
+    /// `update_note` records MySQL's copy as a revision when the local edit
+    /// wins, and a losing local edit is recorded as a revision directly.
+    async fn resolve(&self, local: &Note, remote: Note) -> Result<(), AppError> {
+        let id = remote.id.unwrap_or_default();
+
+        if local.updated_at > remote.updated_at {
+            if remote.deleted_at.is_some() {
+                self.notes.restore_note(id).await?;
+            }
+            self.notes.update_note(id, &Note { version: None, ..local.clone() }).await?;
+        } else {
+            let mut conn = self.pool.get_conn().await?;
+            revisions::record_revision(&mut conn, id, &local.title, local.content.as_deref()).await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the mirror with MySQL's notes, trash included. Returns
+    /// whether any cached note changed.
+    async fn pull(&self) -> Result<bool, AppError> {
+        let mut notes = self.notes.get_all_notes().await?;
+        notes.extend(self.notes.list_trash().await?);
+        self.cache.replace_mirror(notes).await
+    }
+}
+
+#[async_trait]
+impl NoteTarget for SyncContext {
+    type Pending = Transaction<'static>;
+
+    async fn insert(&self, note: &Note) -> Result<(i64, Self::Pending), AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+        let id = notes::insert_note(&mut tx, note).await?.id.unwrap_or_default();
+        if let Some(deleted_at) = &note.deleted_at {
+            // `updated_at = updated_at` stops ON UPDATE from bumping the edit time.
+            tx.exec_drop(
+                r"UPDATE notes SET deleted_at = :deleted_at, updated_at = updated_at WHERE id = :id",
+                params! { "deleted_at" => timestamps::to_value(deleted_at), "id" => id },
+            ).await?;
+        }
+        Ok((id, tx))
+    }
+
+    async fn commit(&self, pending: Self::Pending) -> Result<(), AppError> {
+        Ok(pending.commit().await?)
+    }
+
+    async fn find(&self, id: i64) -> Result<Option<Note>, AppError> {
+        self.notes.find_note(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+    use crate::store::NoteStore;
+
+    /// A cache in a temp directory, removed on drop.
+    struct TempCache {
+        _dir: TempDir,
+        store: SqliteStore,
+    }
+
+    impl TempCache {
+        fn new() -> Self {
+            let dir = TempDir::new().unwrap();
+            let store = SqliteStore::open(&dir.path().join("notes.sqlite3")).unwrap();
+            Self { _dir: dir, store }
+        }
+
+        async fn with_notes(titles: &[&str]) -> Self {
+            let cache = Self::new();
+            for title in titles {
+                cache.store.create_note(&Note::new(title.to_string(), None)).await.unwrap();
+            }
+            cache
+        }
+    }
+
+    /// MySQL as seen by `push_new`. `lose_commit_reply` makes a commit
+    /// succeed but report an error, like a connection lost mid-reply.
+    #[derive(Default)]
+    struct FakeMySql {
+        notes: std::sync::Mutex<Vec<Note>>,
+        lose_commit_reply: bool,
+        fail_commit: bool,
+    }
+
+    #[async_trait]
+    impl NoteTarget for FakeMySql {
+        type Pending = Note;
+
+        async fn insert(&self, note: &Note) -> Result<(i64, Note), AppError> {
+            let id = self.notes.lock().unwrap().len() as i64 + 1;
+            Ok((id, Note { id: Some(id), ..note.clone() }))
+        }
+
+        async fn commit(&self, pending: Note) -> Result<(), AppError> {
+            if self.fail_commit {
+                return Err(AppError::Internal("Deadlock found when trying to get lock".into()));
+            }
+            self.notes.lock().unwrap().push(pending);
+            if self.lose_commit_reply {
+                return Err(AppError::Internal("Lost connection to MySQL server during query".into()));
+            }
+            Ok(())
+        }
+
+        async fn find(&self, id: i64) -> Result<Option<Note>, AppError> {
+            Ok(self.notes.lock().unwrap().iter().find(|n| n.id == Some(id)).cloned())
+        }
+    }
+
+    /// Pushes every pending create, as a sync would.
+    async fn push_all(target: &impl NoteTarget, cache: &SqliteStore) -> Result<u32, AppError> {
+        replay_outbox(cache, |entry| async move {
+            if let Some(local) = cache.find_note(entry.note_id).await? {
+                push_new(target, cache, &entry, &local).await?;
+            }
+            Ok(false)
+        }).await
+    }
+
+    #[tokio::test]
+    async fn a_create_that_reached_mysql_is_not_pushed_twice() {
+        let cache = TempCache::with_notes(&["Written offline"]).await;
+
+        let remote = FakeMySql { lose_commit_reply: true, ..Default::default() };
+        assert!(push_all(&remote, &cache.store).await.is_err());
+        assert_eq!(cache.store.pending_count().await.unwrap(), 1);
+
+        let remote = FakeMySql { notes: remote.notes, ..Default::default() };
+        push_all(&remote, &cache.store).await.unwrap();
+
+        let pushed = remote.notes.lock().unwrap().clone();
+        assert_eq!(pushed.len(), 1);
+        assert_eq!(cache.store.pending_count().await.unwrap(), 0);
+        let cached = cache.store.get_all_notes().await.unwrap();
+        assert_eq!(cached.iter().map(|n| n.id).collect::<Vec<_>>(), [Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn a_create_rolled_back_in_mysql_is_pushed_again() {
+        let cache = TempCache::with_notes(&["Written offline"]).await;
+
+        let remote = FakeMySql { fail_commit: true, ..Default::default() };
+        assert!(push_all(&remote, &cache.store).await.is_err());
+        assert!(cache.store.pending_ops().await.unwrap()[0].remote_id.is_some());
+
+        let remote = FakeMySql::default();
+        push_all(&remote, &cache.store).await.unwrap();
+
+        assert_eq!(remote.notes.lock().unwrap().len(), 1);
+        assert_eq!(cache.store.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn keeps_entries_that_failed_to_apply() {
+        let cache = TempCache::with_notes(&["Written offline", "Also offline"]).await;
+        let attempts = AtomicUsize::new(0);
+
+        let result = replay_outbox(&cache.store, |_| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(AppError::Internal("Deadlock found when trying to get lock".into())) }
+        }).await;
+
+        assert!(matches!(result, Err(AppError::Internal(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.store.pending_count().await.unwrap(), 2);
+        assert_eq!(cache.store.get_all_notes().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_entries_with_unresolved_conflicts() {
+        let cache = TempCache::with_notes(&["Written offline"]).await;
+
+        let result = replay_outbox(&cache.store, |_| async {
+            Err(AppError::Conflict { message: "Duplicate entry".into(), current: None })
+        }).await;
+
+        assert!(matches!(result, Err(AppError::Conflict { .. })));
+        assert_eq!(cache.store.pending_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_rejected_creates_as_failed() {
+        let cache = TempCache::with_notes(&["Purged elsewhere", "Invalid", "Fine"]).await;
+        let rejected = |entry: OutboxEntry| async move {
+            match entry.id {
+                1 => Err(AppError::not_found("Note")),
+                2 => Err(AppError::validation("Title must not be empty")),
+                _ => Ok(false),
+            }
+        };
+
+        let conflicts = replay_outbox(&cache.store, rejected).await.unwrap();
+
+        assert_eq!(conflicts, 0);
+        assert_eq!(cache.store.pending_count().await.unwrap(), 2);
+        assert_eq!(cache.store.failed_count().await.unwrap(), 2);
+        assert_eq!(cache.store.get_all_notes().await.unwrap().len(), 3);
+
+        // Retried on the next sync, and gone once MySQL accepts them.
+        replay_outbox(&cache.store, |_| async { Ok(false) }).await.unwrap();
+        assert_eq!(cache.store.pending_count().await.unwrap(), 0);
+        assert_eq!(cache.store.failed_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn drops_other_entries_that_can_never_apply() {
+        let cache = TempCache::new();
+        let note = cache.store.create_note(&Note::new("Pushed".into(), None)).await.unwrap();
+        let local_id = note.id.unwrap();
+        replay_outbox(&cache.store, |_| async { Ok(false) }).await.unwrap();
+        cache.store.mirror_note(&Note { id: Some(1), ..note }).await.unwrap();
+        cache.store.forget_note(local_id).await.unwrap();
+        cache.store.delete_note(1).await.unwrap();
+
+        let ops = cache.store.pending_ops().await.unwrap();
+        assert_eq!(ops.iter().map(|e| e.op).collect::<Vec<_>>(), [OutboxOp::Delete]);
+
+        replay_outbox(&cache.store, |_| async { Err(AppError::not_found("Note")) }).await.unwrap();
+
+        assert_eq!(cache.store.pending_count().await.unwrap(), 0);
+        assert_eq!(cache.store.failed_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn counts_resolved_conflicts() {
+        let cache = TempCache::with_notes(&["First", "Second"]).await;
+
+        let conflicts = replay_outbox(&cache.store, |entry| async move { Ok(entry.id == 2) }).await.unwrap();
+
+        assert_eq!(conflicts, 1);
+        assert_eq!(cache.store.pending_count().await.unwrap(), 0);
+    }
+}
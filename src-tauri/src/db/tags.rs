@@ -0,0 +1,211 @@
+// src/db/tags.rs
+
+use std::collections::{HashMap, HashSet};
+use mysql_async::{prelude::*, Pool, Params, TxOpts, Value, Error as MySqlError};
+use anyhow::Result;
+use crate::error::AppError;
+use crate::models::{Note, TagUsage};
+
+pub struct TagRepository {
+    pool: Pool,
+}
+
+impl TagRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds tags to a note, creating any tag that does not exist yet.
+    /// Returns the note's full tag list afterwards.
+    pub async fn add_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        ensure_note_exists(&mut tx, note_id).await?;
+        attach_tags(&mut tx, note_id, tags).await?;
+        let current = tags_for_note(&mut tx, note_id).await?;
+
+        tx.commit().await?;
+        Ok(current)
+    }
+
+    /// Removes tags from a note. Tags no longer used by any note are deleted.
+    /// Returns the note's remaining tags.
+    pub async fn remove_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        ensure_note_exists(&mut tx, note_id).await?;
+        for name in normalize_tags(tags) {
+            tx.exec_drop(
+                r"DELETE nt FROM note_tags nt
+                  JOIN tags t ON t.id = nt.tag_id
+                  WHERE nt.note_id = :note_id AND t.name = :name",
+                params! { "note_id" => note_id, "name" => name },
+            ).await?;
+        }
+        delete_unused_tags(&mut tx).await?;
+        let current = tags_for_note(&mut tx, note_id).await?;
+
+        tx.commit().await?;
+        Ok(current)
+    }
+
+    /// Renames a tag on every note. Renaming onto an existing tag merges the two.
+    pub async fn rename_tag(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let to = normalize_tags(&[to.to_string()]).pop()
+            .ok_or_else(|| AppError::validation("Tag name must not be empty"))?;
+
+        let mut tx = self.pool.start_transaction(TxOpts::default()).await?;
+
+        let from_id: i64 = tx.exec_first(r"SELECT id FROM tags WHERE name = :name", params! { "name" => from.trim() })
+            .await?
+            .ok_or_else(|| AppError::not_found("Tag"))?;
+        let to_id: Option<i64> = tx.exec_first(r"SELECT id FROM tags WHERE name = :name", params! { "name" => &to })
+            .await?;
+
+        match to_id {
+            Some(to_id) if to_id != from_id => {
+                tx.exec_drop(
+                    r"INSERT IGNORE INTO note_tags (note_id, tag_id)
+                      SELECT note_id, :to_id FROM note_tags WHERE tag_id = :from_id",
+                    params! { "to_id" => to_id, "from_id" => from_id },
+                ).await?;
+                tx.exec_drop(r"DELETE FROM tags WHERE id = :id", params! { "id" => from_id }).await?;
+            },
+            // Same tag (possibly a change of case only) or a brand new name.
+            _ => {
+                tx.exec_drop(
+                    r"UPDATE tags SET name = :name WHERE id = :id",
+                    params! { "name" => &to, "id" => from_id },
+                ).await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn list_tags(&self) -> Result<Vec<TagUsage>, AppError> {
+        let mut conn = self.pool.get_conn().await?;
+
+        // Notes in the trash do not count towards a tag's usage.
+        let query = r"SELECT t.name, COUNT(n.id)
+                      FROM tags t
+                      LEFT JOIN note_tags nt ON nt.tag_id = t.id
+                      LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+                      GROUP BY t.id, t.name
+                      ORDER BY t.name";
+        let rows: Vec<(String, u64)> = conn.query(query).await?;
+
+        Ok(rows.into_iter()
+            .map(|(name, note_count)| TagUsage { name, note_count })
+            .collect())
+    }
+}
+
+/// Trims tag names, drops empty ones and removes case-insensitive duplicates
+/// (the `tags.name` collation treats "Work" and "work" as the same tag).
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// Tags in `from` that `to` lacks, compared case-insensitively like the
+/// `tags.name` collation.
+pub fn missing_tags(from: &[String], to: &[String]) -> Vec<String> {
+    let existing: HashSet<String> = to.iter().map(|t| t.to_lowercase()).collect();
+    from.iter()
+        .filter(|t| !existing.contains(&t.to_lowercase()))
+        .cloned()
+        .collect()
+}
+
+pub async fn attach_tags<Q: Queryable>(conn: &mut Q, note_id: i64, tags: &[String]) -> Result<(), MySqlError> {
+    for name in normalize_tags(tags) {
+        conn.exec_drop(r"INSERT IGNORE INTO tags (name) VALUES (:name)", params! { "name" => &name }).await?;
+        conn.exec_drop(
+            r"INSERT IGNORE INTO note_tags (note_id, tag_id)
+              SELECT :note_id, id FROM tags WHERE name = :name",
+            params! { "note_id" => note_id, "name" => &name },
+        ).await?;
+    }
+    Ok(())
+}
+
+/// Replaces a note's tags with `tags`. Tags no longer used by any note are
+/// deleted.
+pub async fn set_tags<Q: Queryable>(conn: &mut Q, note_id: i64, tags: &[String]) -> Result<(), MySqlError> {
+    conn.exec_drop(r"DELETE FROM note_tags WHERE note_id = :note_id", params! { "note_id" => note_id }).await?;
+    attach_tags(conn, note_id, tags).await?;
+    delete_unused_tags(conn).await
+}
+
+pub async fn tags_for_note<Q: Queryable>(conn: &mut Q, note_id: i64) -> Result<Vec<String>, MySqlError> {
+    conn.exec(
+        r"SELECT t.name FROM note_tags nt
+          JOIN tags t ON t.id = nt.tag_id
+          WHERE nt.note_id = :note_id
+          ORDER BY t.name",
+        params! { "note_id" => note_id },
+    ).await
+}
+
+/// Fills in `tags` for every note with a single query.
+pub async fn load_tags<Q: Queryable>(conn: &mut Q, notes: &mut [Note]) -> Result<(), MySqlError> {
+    let ids: Vec<i64> = notes.iter().filter_map(|n| n.id).collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let (placeholders, params) = named_list("id", ids.into_iter().map(Value::from));
+    let query = format!(
+        "SELECT nt.note_id, t.name FROM note_tags nt \
+         JOIN tags t ON t.id = nt.tag_id \
+         WHERE nt.note_id IN ({}) \
+         ORDER BY t.name",
+        placeholders
+    );
+    let rows: Vec<(i64, String)> = conn.exec(query, params).await?;
+
+    let mut by_note: HashMap<i64, Vec<String>> = HashMap::new();
+    for (note_id, name) in rows {
+        by_note.entry(note_id).or_default().push(name);
+    }
+    for note in notes.iter_mut() {
+        if let Some(id) = note.id {
+            note.tags = by_note.remove(&id).unwrap_or_default();
+        }
+    }
+    Ok(())
+}
+
+/// Builds `:prefix0, :prefix1, ...` placeholders and the matching named
+/// params for an `IN (...)` list.
+pub fn named_list(prefix: &str, values: impl IntoIterator<Item = Value>) -> (String, Params) {
+    let mut placeholders = Vec::new();
+    let mut params = HashMap::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let name = format!("{}{}", prefix, i);
+        placeholders.push(format!(":{}", name));
+        params.insert(name.into_bytes(), value);
+    }
+    (placeholders.join(", "), Params::Named(params))
+}
+
+pub async fn ensure_note_exists<Q: Queryable>(conn: &mut Q, note_id: i64) -> Result<(), AppError> {
+    let exists: Option<i64> = conn.exec_first(r"SELECT id FROM notes WHERE id = :id AND deleted_at IS NULL", params! { "id" => note_id }).await?;
+    exists.map(|_| ()).ok_or_else(|| AppError::not_found("Note"))
+}
+
+async fn delete_unused_tags<Q: Queryable>(conn: &mut Q) -> Result<(), MySqlError> {
+    conn.query_drop(
+        r"DELETE t FROM tags t
+          LEFT JOIN note_tags nt ON nt.tag_id = t.id
+          WHERE nt.tag_id IS NULL"
+    ).await
+}
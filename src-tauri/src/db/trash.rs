@@ -0,0 +1,57 @@
+// src/db/trash.rs
+
+use std::env;
+use dotenv::dotenv;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tauri::Manager;
+use super::DatabaseState;
+
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long trashed notes are kept before the purger removes them for good.
+/// Seeded from `TRASH_RETENTION_DAYS` and adjustable at runtime.
+pub struct TrashSettings {
+    retention_days: AtomicU32,
+}
+
+impl TrashSettings {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let retention_days = env::var("TRASH_RETENTION_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+        Self { retention_days: AtomicU32::new(retention_days) }
+    }
+
+    pub fn retention_days(&self) -> u32 {
+        self.retention_days.load(Ordering::Relaxed)
+    }
+
+    pub fn set_retention_days(&self, days: u32) {
+        self.retention_days.store(days, Ordering::Relaxed);
+    }
+}
+
+/// Periodically purges expired trash from whichever store is active. Runs
+/// for the lifetime of the app.
+pub async fn run_trash_purger(app: tauri::AppHandle) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let store = app.state::<DatabaseState>().store().await;
+
+        let retention_days = app.state::<TrashSettings>().retention_days();
+        match store.purge_expired_trash(retention_days).await {
+            Ok(0) => {},
+            Ok(purged) => log::info!("Purged {} note(s) older than {} days from the trash", purged, retention_days),
+            Err(e) => log::warn!("Trash purge failed: {}", e),
+        }
+    }
+}
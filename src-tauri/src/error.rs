@@ -0,0 +1,154 @@
+// src/error.rs
+
+use std::fmt;
+use std::path::Path;
+use mysql_async::Error as MySqlError;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use crate::models::Note;
+use crate::validation::FieldError;
+
+/// MySQL server error for a duplicate key (`ER_DUP_ENTRY`).
+const ER_DUP_ENTRY: u16 = 1062;
+
+/// Error returned by every Tauri command.
+///
+/// Serialized as `{ code, message, fields, current }` where `code` is one of
+/// the stable strings from `AppError::code`, so the frontend can branch on it
+/// without parsing the message.
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    /// Bad input. `fields` names the offending payload fields, if any.
+    Validation {
+        message: String,
+        fields: Vec<FieldError>,
+    },
+    /// The write lost a race or would duplicate a unique value. When the
+    /// conflict is a stale note edit, carries the note as currently stored.
+    Conflict {
+        message: String,
+        current: Option<Box<Note>>,
+    },
+    DatabaseUnavailable(String),
+    SetupRequired(String),
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found(what: &str) -> Self {
+        AppError::NotFound(format!("{} not found", what))
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation { message: message.into(), fields: Vec::new() }
+    }
+
+    /// A failed file operation, naming the file.
+    pub fn io(path: &Path, e: std::io::Error) -> Self {
+        AppError::Internal(format!("{}: {}", path.display(), e))
+    }
+
+    pub fn invalid_fields(fields: Vec<FieldError>) -> Self {
+        let message = fields.iter()
+            .map(|f| f.message.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        AppError::Validation { message, fields }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Validation { .. } => "VALIDATION",
+            AppError::Conflict { .. } => "CONFLICT",
+            AppError::DatabaseUnavailable(_) => "DATABASE_UNAVAILABLE",
+            AppError::SetupRequired(_) => "SETUP_REQUIRED",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Validation { message, .. }
+            | AppError::Conflict { message, .. }
+            | AppError::DatabaseUnavailable(message)
+            | AppError::SetupRequired(message)
+            | AppError::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let current = match self {
+            AppError::Conflict { current, .. } => current.as_deref(),
+            _ => None,
+        };
+        let fields: &[FieldError] = match self {
+            AppError::Validation { fields, .. } => fields,
+            _ => &[],
+        };
+
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("fields", fields)?;
+        state.serialize_field("current", &current)?;
+        state.end()
+    }
+}
+
+impl From<MySqlError> for AppError {
+    fn from(e: MySqlError) -> Self {
+        match &e {
+            MySqlError::Io(_) => AppError::DatabaseUnavailable(e.to_string()),
+            MySqlError::Server(server) if server.code == ER_DUP_ENTRY => AppError::Conflict {
+                message: server.message.clone(),
+                current: None,
+            },
+            _ => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::ConstraintViolation) => AppError::Conflict {
+                message: e.to_string(),
+                current: None,
+            },
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                AppError::DatabaseUnavailable(e.to_string())
+            },
+            _ => AppError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<MySqlError>() {
+            Ok(e) => e.into(),
+            Err(e) => match e.downcast::<AppError>() {
+                Ok(e) => e,
+                Err(e) => AppError::Internal(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
@@ -0,0 +1,72 @@
+// src/events.rs
+
+//! Note change events, so every open window can keep its view of the notes
+//! current without polling.
+//!
+//! `note-created`, `note-updated` and `note-deleted` carry the note as
+//! stored after the change. A note moved to the trash is reported as
+//! deleted, with `deleted_at` set, and one restored from the trash as
+//! created. `notes-reloaded` carries nothing and means any note may have
+//! changed, such as after a sync rewrote the local copy.
+
+use tauri::Emitter;
+use crate::models::{BatchResult, Note};
+
+pub const NOTE_CREATED: &str = "note-created";
+pub const NOTE_UPDATED: &str = "note-updated";
+pub const NOTE_DELETED: &str = "note-deleted";
+pub const NOTES_RELOADED: &str = "notes-reloaded";
+
+/// Publishes note changes to every webview. The default instance has no
+/// app handle and publishes nothing, for repositories used by background
+/// work such as sync.
+#[derive(Clone, Default)]
+pub struct NoteEvents {
+    app: Option<tauri::AppHandle>,
+}
+
+impl NoteEvents {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app: Some(app) }
+    }
+
+    pub fn created(&self, note: &Note) {
+        self.emit(NOTE_CREATED, note);
+    }
+
+    pub fn updated(&self, note: &Note) {
+        self.emit(NOTE_UPDATED, note);
+    }
+
+    pub fn deleted(&self, note: &Note) {
+        self.emit(NOTE_DELETED, note);
+    }
+
+    pub fn reloaded(&self) {
+        let Some(app) = &self.app else {
+            return;
+        };
+        if let Err(e) = app.emit(NOTES_RELOADED, ()) {
+            log::warn!("Failed to emit {}: {}", NOTES_RELOADED, e);
+        }
+    }
+
+    /// Publishes `event` for every note of a committed batch.
+    pub fn batch(&self, event: &str, result: &BatchResult) {
+        if !result.committed {
+            return;
+        }
+        for note in result.items.iter().filter_map(|item| item.note.as_ref()) {
+            self.emit(event, note);
+        }
+    }
+
+    fn emit(&self, event: &str, note: &Note) {
+        let Some(app) = &self.app else {
+            return;
+        };
+        if let Err(e) = app.emit(event, note) {
+            log::warn!("Failed to emit {} for note {:?}: {}", event, note.id, e);
+        }
+    }
+}
@@ -4,96 +4,502 @@
 mod windows_setup;
 #[cfg(target_os = "linux")]
 mod ubuntu_setup;
+mod command_runner;
 mod db;
+mod error;
+mod events;
 mod models;
+mod render;
+mod search;
+mod setup;
+mod store;
+mod timestamps;
+mod transfer;
+mod validation;
 
 use std::env;
+use std::path::PathBuf;
 use log::{info, debug, error};
 use anyhow::Result;
 use tauri::{State, Manager};
 use tauri::Emitter;
-use mysql_async::Pool;
 
-#[cfg(target_os = "linux")]
-use crate::ubuntu_setup::{InstallationStage, UbuntuSystemSetup};
-use crate::db::notes::NoteRepository;
-use crate::models::Note;
+use crate::db::attachments;
+use crate::db::backup::{self, BackupInfo, BackupSettings};
+use crate::db::migrations::{AppliedMigration, MigrationRunner, MigrationStatus};
+use crate::db::revisions::RevisionRepository;
+use crate::db::sync::{self, SyncState, SyncStatus};
+use crate::db::trash::{self, TrashSettings};
+use crate::db::{DatabaseConfigUpdate, DatabaseState, DatabaseStatus};
+use crate::error::AppError;
+use crate::events::NoteEvents;
+use crate::store::{SqliteStore, StorageBackend};
+use crate::models::{
+    Attachment, BatchResult, BulkExportSummary, DeleteResult, ExportSummary, ImportMode, ImportSummary, Note, NoteListQuery, NotePage, NoteRevision, RenderedNote, RevisionDiff, SearchQuery, SearchResults, TagUsage,
+};
 
 #[tauri::command]
 async fn create_note(
-    pool: State<'_, Pool>,
+    db: State<'_, DatabaseState>,
     note: Note
-) -> Result<Note, String> {
-    let repo = NoteRepository::new(pool.inner().clone());
-    repo.create_note(&note)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Note, AppError> {
+    let note = validation::validate_note(&note)?;
+    let store = db.store().await;
+    store.create_note(&note).await
 }
 
 #[tauri::command]
 async fn get_all_notes(
-    pool: State<'_, Pool>
-) -> Result<Vec<Note>, String> {
-    let repo = NoteRepository::new(pool.inner().clone());
-    repo.get_all_notes()
-        .await
-        .map_err(|e| e.to_string())
+    db: State<'_, DatabaseState>
+) -> Result<Vec<Note>, AppError> {
+    let store = db.store().await;
+    store.get_all_notes().await
+}
+
+#[tauri::command]
+async fn list_notes(
+    db: State<'_, DatabaseState>,
+    query: NoteListQuery
+) -> Result<NotePage, AppError> {
+    let store = db.store().await;
+    store.list_notes(&query).await
 }
 
 #[tauri::command]
 async fn get_note_by_id(
-    pool: State<'_, Pool>, 
+    db: State<'_, DatabaseState>, 
     id: i64
-) -> Result<Note, String> {
-    let repo = NoteRepository::new(pool.inner().clone());
-    repo.get_note_by_id(id)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Note, AppError> {
+    let store = db.store().await;
+    store.get_note_by_id(id).await
 }
 
 #[tauri::command]
 async fn update_note(
-    pool: State<'_, Pool>, 
+    db: State<'_, DatabaseState>, 
     id: i64, 
     note: Note
-) -> Result<Note, String> {
-    let repo = NoteRepository::new(pool.inner().clone());
-    repo.update_note(id, &note)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<Note, AppError> {
+    let note = validation::validate_note(&note)?;
+    let store = db.store().await;
+    store.update_note(id, &note).await
 }
 
 #[tauri::command]
 async fn delete_note(
-    pool: State<'_, Pool>, 
+    db: State<'_, DatabaseState>, 
     id: i64
-) -> Result<bool, String> {
-    let repo = NoteRepository::new(pool.inner().clone());
-    repo.delete_note(id)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<DeleteResult, AppError> {
+    let store = db.store().await;
+    store.delete_note(id).await
 }
 
-#[cfg(target_os = "linux")]
 #[tauri::command]
-async fn start_system_setup(app: tauri::AppHandle) -> Result<(), String> {
-    app.emit("installation-stage", InstallationStage::NotStarted)
-        .map_err(|e| e.to_string())?;
-        
-    UbuntuSystemSetup::setup_ubuntu_system_with_events(&app)
-        .await
-        .map_err(|e| e.to_string())
+async fn batch_create_notes(
+    db: State<'_, DatabaseState>,
+    notes: Vec<Note>
+) -> Result<BatchResult, AppError> {
+    let notes = match validate_batch(&notes) {
+        Ok(notes) => notes,
+        Err(rejected) => return Ok(rejected),
+    };
+    let store = db.store().await;
+    store.batch_create_notes(&notes).await
+}
+
+#[tauri::command]
+async fn batch_update_notes(
+    db: State<'_, DatabaseState>,
+    notes: Vec<Note>
+) -> Result<BatchResult, AppError> {
+    let notes = match validate_batch(&notes) {
+        Ok(notes) => notes,
+        Err(rejected) => return Ok(rejected),
+    };
+    let store = db.store().await;
+    store.batch_update_notes(&notes).await
+}
+
+#[tauri::command]
+async fn batch_delete_notes(
+    db: State<'_, DatabaseState>,
+    ids: Vec<i64>
+) -> Result<BatchResult, AppError> {
+    let store = db.store().await;
+    store.batch_delete_notes(&ids).await
+}
+
+/// Validates every note of a batch up front. If any is invalid, nothing is
+/// written and the batch result names each offending item.
+fn validate_batch(notes: &[Note]) -> Result<Vec<Note>, BatchResult> {
+    let mut valid = Vec::with_capacity(notes.len());
+    let mut errors = Vec::new();
+    for (index, note) in notes.iter().enumerate() {
+        match validation::validate_note(note) {
+            Ok(note) => valid.push(note),
+            Err(e) => errors.push((index, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(valid)
+    } else {
+        Err(BatchResult::failed(notes.iter().map(|n| n.id).collect(), errors))
+    }
+}
+
+#[tauri::command]
+fn render_note_html(content: String) -> RenderedNote {
+    render::render_markdown(&content)
+}
+
+#[tauri::command]
+async fn list_note_revisions(
+    db: State<'_, DatabaseState>,
+    note_id: i64
+) -> Result<Vec<NoteRevision>, AppError> {
+    let repo = RevisionRepository::new(db.pool().await?);
+    repo.list_revisions(note_id).await
+}
+
+#[tauri::command]
+async fn diff_note_revisions(
+    db: State<'_, DatabaseState>,
+    note_id: i64,
+    from_revision: u32,
+    to_revision: Option<u32>
+) -> Result<RevisionDiff, AppError> {
+    let repo = RevisionRepository::new(db.pool().await?);
+    repo.diff_revisions(note_id, from_revision, to_revision).await
+}
+
+#[tauri::command]
+async fn restore_note_revision(
+    db: State<'_, DatabaseState>,
+    note_id: i64,
+    revision: u32
+) -> Result<Note, AppError> {
+    let repo = RevisionRepository::new(db.pool().await?);
+    let note = repo.restore_revision(note_id, revision).await?;
+    db.events().updated(&note);
+    Ok(note)
+}
+
+#[tauri::command]
+async fn list_trash(
+    db: State<'_, DatabaseState>
+) -> Result<Vec<Note>, AppError> {
+    let store = db.store().await;
+    store.list_trash().await
+}
+
+#[tauri::command]
+async fn restore_note(
+    db: State<'_, DatabaseState>,
+    id: i64
+) -> Result<Note, AppError> {
+    let store = db.store().await;
+    store.restore_note(id).await
+}
+
+#[tauri::command]
+async fn purge_note(
+    db: State<'_, DatabaseState>,
+    id: i64
+) -> Result<DeleteResult, AppError> {
+    let store = db.store().await;
+    store.purge_note(id).await
+}
+
+#[tauri::command]
+async fn add_attachment(
+    app: tauri::AppHandle,
+    note_id: i64,
+    path: PathBuf
+) -> Result<Attachment, AppError> {
+    let repo = attachments::repository(&app).await?;
+    repo.add_attachment(note_id, &path).await
+}
+
+#[tauri::command]
+async fn list_attachments(
+    app: tauri::AppHandle,
+    note_id: i64
+) -> Result<Vec<Attachment>, AppError> {
+    let repo = attachments::repository(&app).await?;
+    repo.list_attachments(note_id).await
+}
+
+#[tauri::command]
+async fn open_attachment(app: tauri::AppHandle, id: i64) -> Result<(), AppError> {
+    attachments::open_attachment(&app, id).await
+}
+
+#[tauri::command]
+async fn delete_attachment(
+    app: tauri::AppHandle,
+    id: i64
+) -> Result<DeleteResult, AppError> {
+    let repo = attachments::repository(&app).await?;
+    repo.delete_attachment(id).await
+}
+
+#[tauri::command]
+fn get_trash_retention_days(settings: State<'_, TrashSettings>) -> u32 {
+    settings.retention_days()
+}
+
+#[tauri::command]
+fn set_trash_retention_days(settings: State<'_, TrashSettings>, days: u32) -> Result<(), AppError> {
+    if days == 0 {
+        return Err(AppError::validation("Retention must be at least one day"));
+    }
+    settings.set_retention_days(days);
+    Ok(())
+}
+
+#[tauri::command]
+async fn backup_database(app: tauri::AppHandle) -> Result<BackupInfo, AppError> {
+    backup::backup_database(&app).await
+}
+
+#[tauri::command]
+async fn restore_database(app: tauri::AppHandle, file_name: String) -> Result<(), AppError> {
+    backup::restore_database(&app, &file_name).await?;
+
+    // The local cache still mirrors the data that was just replaced.
+    tauri::async_runtime::spawn(sync_after_connect(app.clone()));
+    Ok(())
+}
+
+#[tauri::command]
+async fn list_backups(app: tauri::AppHandle) -> Result<Vec<BackupInfo>, AppError> {
+    backup::list_backups(&app).await
+}
+
+#[tauri::command]
+fn get_backup_keep(settings: State<'_, BackupSettings>) -> u32 {
+    settings.keep()
+}
+
+#[tauri::command]
+fn set_backup_keep(settings: State<'_, BackupSettings>, keep: u32) -> Result<(), AppError> {
+    if keep == 0 {
+        return Err(AppError::validation("At least one backup must be kept"));
+    }
+    settings.set_keep(keep);
+    Ok(())
+}
+
+#[tauri::command]
+async fn search_notes(
+    db: State<'_, DatabaseState>,
+    query: SearchQuery
+) -> Result<SearchResults, AppError> {
+    let store = db.store().await;
+    store.search_notes(&query).await
+}
+
+#[tauri::command]
+async fn add_note_tags(
+    db: State<'_, DatabaseState>,
+    note_id: i64,
+    tags: Vec<String>
+) -> Result<Vec<String>, AppError> {
+    let tags = validation::validate_tags(&tags)?;
+    let store = db.store().await;
+    store.add_tags(note_id, &tags).await
+}
+
+#[tauri::command]
+async fn remove_note_tags(
+    db: State<'_, DatabaseState>,
+    note_id: i64,
+    tags: Vec<String>
+) -> Result<Vec<String>, AppError> {
+    let store = db.store().await;
+    store.remove_tags(note_id, &tags).await
+}
+
+#[tauri::command]
+async fn rename_tag(
+    db: State<'_, DatabaseState>,
+    from: String,
+    to: String
+) -> Result<(), AppError> {
+    let to = validation::validate_tag_name(&to, "to")?;
+    let store = db.store().await;
+    store.rename_tag(&from, &to).await
+}
+
+#[tauri::command]
+async fn list_tags(
+    db: State<'_, DatabaseState>
+) -> Result<Vec<TagUsage>, AppError> {
+    let store = db.store().await;
+    store.list_tags().await
+}
+
+#[tauri::command]
+async fn get_notes_by_tags(
+    db: State<'_, DatabaseState>,
+    tags: Vec<String>,
+    match_all: bool
+) -> Result<Vec<Note>, AppError> {
+    let store = db.store().await;
+    store.get_notes_by_tags(&tags, match_all).await
+}
+
+#[tauri::command]
+async fn export_notes(
+    db: State<'_, DatabaseState>,
+    dir: PathBuf
+) -> Result<ExportSummary, AppError> {
+    let store = db.store().await;
+    transfer::markdown::export_notes(store.as_ref(), &dir).await
+}
+
+#[tauri::command]
+async fn import_notes(
+    db: State<'_, DatabaseState>,
+    dir: PathBuf,
+    mode: Option<ImportMode>
+) -> Result<ImportSummary, AppError> {
+    let store = db.store().await;
+    transfer::markdown::import_notes(store.as_ref(), &dir, mode.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn export_notes_json(
+    app: tauri::AppHandle,
+    db: State<'_, DatabaseState>,
+    path: PathBuf
+) -> Result<BulkExportSummary, AppError> {
+    let store = db.store().await;
+    transfer::json::export_notes(&app, store.as_ref(), &path).await
+}
+
+#[tauri::command]
+async fn import_notes_json(
+    app: tauri::AppHandle,
+    db: State<'_, DatabaseState>,
+    path: PathBuf,
+    mode: Option<ImportMode>
+) -> Result<ImportSummary, AppError> {
+    let store = db.store().await;
+    transfer::json::import_notes(&app, store.as_ref(), &path, mode.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn export_notes_csv(
+    app: tauri::AppHandle,
+    db: State<'_, DatabaseState>,
+    path: PathBuf
+) -> Result<BulkExportSummary, AppError> {
+    let store = db.store().await;
+    transfer::csv::export_notes(&app, store.as_ref(), &path).await
+}
+
+#[tauri::command]
+async fn import_notes_csv(
+    app: tauri::AppHandle,
+    db: State<'_, DatabaseState>,
+    path: PathBuf,
+    mode: Option<ImportMode>
+) -> Result<ImportSummary, AppError> {
+    let store = db.store().await;
+    transfer::csv::import_notes(&app, store.as_ref(), &path, mode.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn get_migration_status(
+    db: State<'_, DatabaseState>
+) -> Result<MigrationStatus, AppError> {
+    let runner = MigrationRunner::new(db.pool().await?);
+    Ok(runner.status().await?)
 }
 
-#[cfg(target_os = "windows")]
 #[tauri::command]
-async fn start_system_setup(app: tauri::AppHandle) -> Result<(), String> {
-    app.emit("installation-stage", "NotStarted")
-        .map_err(|e| e.to_string())?;
+async fn rollback_last_migration(
+    db: State<'_, DatabaseState>
+) -> Result<Option<AppliedMigration>, AppError> {
+    let runner = MigrationRunner::new(db.pool().await?);
+    Ok(runner.rollback_last().await?)
+}
+
+#[tauri::command]
+async fn start_system_setup(
+    app: tauri::AppHandle,
+    docker_mode: Option<setup::DockerInstallMode>,
+) -> Result<(), AppError> {
+    let platform = setup::platform_setup(&app, docker_mode.unwrap_or_default());
+    let state = setup::SetupState::load(setup::state_path(&app)?);
+    setup::run_setup(platform.as_ref(), &app, &state).await?;
 
-    windows_setup::WindowsSystemSetup::setup_windows_system(&app)
-        .await
-        .map_err(|e| e.to_string())
+    connect_database(&app).await
+}
+
+/// Where the last setup run got to, so the setup dialog can pick up from
+/// there on launch.
+#[tauri::command]
+fn get_setup_status(app: tauri::AppHandle) -> Result<setup::SetupStatus, AppError> {
+    Ok(setup::SetupState::load(setup::state_path(&app)?).status())
+}
+
+/// Builds the pool once setup has reached `SetupComplete` and tells the
+/// frontend whether the database is usable.
+async fn connect_database(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let db = app.state::<DatabaseState>();
+    let result = db.initialize().await;
+
+    app.emit("database-status", db.status().await)?;
+
+    result.map_err(|e| AppError::DatabaseUnavailable(format!("Database setup failed: {}", e)))?;
+
+    // The container is healthy again; push anything written while it was down.
+    tauri::async_runtime::spawn(sync_after_connect(app.clone()));
+    Ok(())
+}
+
+async fn sync_after_connect(app: tauri::AppHandle) {
+    sync::sync_now(&app).await;
+}
+
+#[tauri::command]
+async fn get_storage_backend(db: State<'_, DatabaseState>) -> Result<StorageBackend, AppError> {
+    Ok(db.store().await.backend())
+}
+
+#[tauri::command]
+async fn get_database_status(db: State<'_, DatabaseState>) -> Result<DatabaseStatus, AppError> {
+    Ok(db.status().await)
+}
+
+#[tauri::command]
+async fn reconfigure_database(
+    app: tauri::AppHandle,
+    db: State<'_, DatabaseState>,
+    config: DatabaseConfigUpdate
+) -> Result<DatabaseStatus, AppError> {
+    let result = db.reconfigure(config).await;
+    let status = db.status().await;
+
+    app.emit("database-status", status.clone())?;
+
+    result.map_err(|e| AppError::DatabaseUnavailable(e.to_string()))?;
+
+    tauri::async_runtime::spawn(sync_after_connect(app.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+fn get_sync_status(sync_state: State<'_, SyncState>) -> SyncStatus {
+    sync_state.status()
+}
+
+#[tauri::command]
+async fn sync_notes(app: tauri::AppHandle) -> Result<SyncStatus, AppError> {
+    Ok(sync::sync_now(&app).await)
 }
 
 #[derive(Debug, PartialEq)]
@@ -183,16 +589,72 @@ pub fn run() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_opener::init())
         .setup(|app| {
+            // Notes live here until MySQL is set up and reachable.
+            let offline = SqliteStore::open(&app.path().app_data_dir()?.join("notes.sqlite3"))?;
+            let setup_complete = setup::SetupState::load(setup::state_path(app.handle())?)
+                .status().stage == setup::InstallationStage::SetupComplete;
+            app.manage(DatabaseState::new(offline, NoteEvents::new(app.handle().clone()), setup_complete));
+            app.manage(TrashSettings::from_env());
+            app.manage(SyncState::new());
+            app.manage(BackupSettings::from_env());
+
+            tauri::async_runtime::spawn(trash::run_trash_purger(app.handle().clone()));
+            tauri::async_runtime::spawn(sync::run_sync_loop(app.handle().clone()));
+            tauri::async_runtime::spawn(backup::run_backup_scheduler(app.handle().clone()));
+            tauri::async_runtime::spawn(attachments::run_attachment_gc(app.handle().clone()));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             create_note,
             get_all_notes,
+            list_notes,
             get_note_by_id,
             update_note,
             delete_note,
+            batch_create_notes,
+            batch_update_notes,
+            batch_delete_notes,
+            list_note_revisions,
+            diff_note_revisions,
+            restore_note_revision,
+            list_trash,
+            restore_note,
+            purge_note,
+            add_attachment,
+            list_attachments,
+            open_attachment,
+            delete_attachment,
+            get_trash_retention_days,
+            set_trash_retention_days,
+            backup_database,
+            restore_database,
+            list_backups,
+            get_backup_keep,
+            set_backup_keep,
+            search_notes,
+            add_note_tags,
+            remove_note_tags,
+            rename_tag,
+            list_tags,
+            get_notes_by_tags,
+            render_note_html,
+            export_notes,
+            import_notes,
+            export_notes_json,
+            import_notes_json,
+            export_notes_csv,
+            import_notes_csv,
+            get_migration_status,
+            rollback_last_migration,
             start_system_setup,
+            get_setup_status,
+            get_database_status,
+            get_storage_backend,
+            get_sync_status,
+            sync_notes,
+            reconfigure_database,
             is_docker_installed,
             get_os_type,
             get_os_details,
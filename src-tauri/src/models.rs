@@ -2,25 +2,29 @@
 
 use serde::{Deserialize, Serialize};
 use mysql_async::{Row, prelude::FromRow, FromRowError, prelude::FromValue};
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Utc};
 use mysql_async::Value;
-
-pub const CREATE_NOTES_TABLE: &str = r#"
-CREATE TABLE IF NOT EXISTS notes (
-    id BIGINT AUTO_INCREMENT PRIMARY KEY,
-    title VARCHAR(255) NOT NULL,
-    content TEXT,
-    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
-)"#;
+use crate::error::AppError;
+use crate::timestamps::{self, rfc3339_option};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
     pub id: Option<i64>,
     pub title: String,
     pub content: Option<String>,
-    pub created_at: Option<String>,
-    pub updated_at: Option<String>,
+    #[serde(default, with = "rfc3339_option")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Set while the note is in the trash.
+    #[serde(default, with = "rfc3339_option")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every update. Send back the version you loaded so a
+    /// concurrent edit is reported as a conflict instead of being overwritten.
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Note {
@@ -31,47 +35,144 @@ impl Note {
             content,
             created_at: None,
             updated_at: None,
+            deleted_at: None,
+            version: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagUsage {
+    pub name: String,
+    pub note_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SearchMode {
+    #[default]
+    NaturalLanguage,
+    Boolean,
+}
+
+impl SearchMode {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SearchMode::NaturalLanguage => "IN NATURAL LANGUAGE MODE",
+            SearchMode::Boolean => "IN BOOLEAN MODE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchQuery {
+    pub query: String,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// 1-based page number.
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub note: Note,
+    pub score: f64,
+    /// Title with matched terms wrapped in `<mark>`; HTML-escaped.
+    pub title_highlight: String,
+    /// Excerpt of the content around the first match, highlighted like the title.
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub total: u64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+/// Note content rendered from Markdown by `render::render_markdown`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedNote {
+    /// Sanitized HTML, safe to insert into the page as is.
+    pub html: String,
+    /// Every heading in document order.
+    pub toc: Vec<TocEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TocEntry {
+    /// 1 to 6, as in `<h1>` to `<h6>`.
+    pub level: u8,
+    pub text: String,
+    /// The heading's `id` in `html`, for `#anchor` links.
+    pub anchor: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum NoteSortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Title,
+}
+
+impl NoteSortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            NoteSortField::CreatedAt => "created_at",
+            NoteSortField::UpdatedAt => "updated_at",
+            NoteSortField::Title => "title",
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NoteListQuery {
+    pub page_size: Option<u32>,
+    /// Opaque cursor from a previous `NotePage`; omit for the first page.
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort_by: NoteSortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+    #[serde(default, with = "rfc3339_option")]
+    pub created_after: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    pub updated_after: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    pub updated_before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NotePage {
+    pub notes: Vec<Note>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    /// Number of notes matching the filters, across all pages.
+    pub total: u64,
+}
+
 impl FromRow for Note {
     fn from_row(row: Row) -> Self {
+        // `title` is NOT NULL and validated on write, so no fallback is needed.
         let title: String = row.get::<Option<String>, _>(1)
             .flatten()
-            .unwrap_or_else(|| String::from("Untitled"));
-
-        // Handle created_at
-        let created_at = row.get::<Option<Value>, _>(3)
-            .and_then(|opt_value| {
-                opt_value.and_then(|value| match value {
-                    Value::Date(year, month, day, hour, minute, second, _) => {
-                        Some(format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", 
-                            year, month, day, hour, minute, second))
-                    },
-                    Value::Bytes(bytes) => {
-                        String::from_utf8_lossy(&bytes).to_string().into()
-                    },
-                    Value::Time(..) => None, // Use `..` to ignore all fields
-                    _ => None
-                })
-            });
-
-        // Handle updated_at
-        let updated_at = row.get::<Option<Value>, _>(4)
-            .and_then(|opt_value| {
-                opt_value.and_then(|value| match value {
-                    Value::Date(year, month, day, hour, minute, second, _) => {
-                        Some(format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", 
-                            year, month, day, hour, minute, second))
-                    },
-                    Value::Bytes(bytes) => {
-                        String::from_utf8_lossy(&bytes).to_string().into()
-                    },
-                    Value::Time(..) => None, // Use `..` to ignore all fields
-                    _ => None
-                })
-            });
+            .unwrap_or_default();
+
+        let created_at = timestamp(&row, 3);
+        let updated_at = timestamp(&row, 4);
+        let deleted_at = timestamp(&row, 5);
 
         Note {
             id: row.get(0),
@@ -79,6 +180,10 @@ impl FromRow for Note {
             content: row.get(2),
             created_at,
             updated_at,
+            deleted_at,
+            version: row.get::<Option<u32>, _>(6).flatten(),
+            // Filled in by `NoteRepository`, which loads tags in a separate query.
+            tags: Vec::new(),
         }
     }
 
@@ -87,14 +192,260 @@ impl FromRow for Note {
     }
 }
 
-impl Note {
-    pub fn created_at_datetime(&self) -> Option<NaiveDateTime> {
-        self.created_at.as_ref()
-            .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+/// Title and content of a note as they were before an update.
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRevision {
+    pub id: i64,
+    pub note_id: i64,
+    pub revision: u32,
+    pub title: String,
+    pub content: Option<String>,
+    #[serde(with = "rfc3339_option")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FromRow for NoteRevision {
+    fn from_row(row: Row) -> Self {
+        NoteRevision {
+            id: row.get(0).unwrap_or_default(),
+            note_id: row.get(1).unwrap_or_default(),
+            revision: row.get(2).unwrap_or_default(),
+            title: row.get::<Option<String>, _>(3).flatten().unwrap_or_default(),
+            content: row.get::<Option<String>, _>(4).flatten(),
+            created_at: timestamp(&row, 5),
+        }
     }
 
-    pub fn updated_at_datetime(&self) -> Option<NaiveDateTime> {
-        self.updated_at.as_ref()
-            .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok())
+    fn from_row_opt(row: Row) -> Result<Self, FromRowError> {
+        Ok(Self::from_row(row))
     }
-}
\ No newline at end of file
+}
+
+/// A file attached to a note. The contents live on disk, named by their
+/// SHA-256, so identical files attached twice are stored once.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub note_id: i64,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    #[serde(with = "rfc3339_option")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl FromRow for Attachment {
+    fn from_row(row: Row) -> Self {
+        Attachment {
+            id: row.get(0).unwrap_or_default(),
+            note_id: row.get(1).unwrap_or_default(),
+            file_name: row.get::<Option<String>, _>(2).flatten().unwrap_or_default(),
+            mime_type: row.get::<Option<String>, _>(3).flatten().unwrap_or_default(),
+            size_bytes: row.get(4).unwrap_or_default(),
+            sha256: row.get::<Option<String>, _>(5).flatten().unwrap_or_default(),
+            created_at: timestamp(&row, 6),
+        }
+    }
+
+    fn from_row_opt(row: Row) -> Result<Self, FromRowError> {
+        Ok(Self::from_row(row))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    /// 1-based line number in the older text, absent for insertions.
+    pub old_line: Option<usize>,
+    /// 1-based line number in the newer text, absent for deletions.
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionDiff {
+    pub from_revision: u32,
+    /// `None` when comparing against the note's current content.
+    pub to_revision: Option<u32>,
+    pub old_title: String,
+    pub new_title: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Result of `export_notes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub directory: String,
+    /// File names written, in the order the notes were listed.
+    pub files: Vec<String>,
+    /// Files deleted because their note was renamed or is in the trash.
+    pub removed: Vec<String>,
+}
+
+/// Result of `export_notes_json` and `export_notes_csv`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkExportSummary {
+    pub path: String,
+    pub exported: u64,
+}
+
+/// How imported notes that carry an id are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ImportMode {
+    /// Update the note with that id if it exists, otherwise create one.
+    #[default]
+    Upsert,
+    /// Ignore ids and create every note.
+    Create,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportError {
+    /// File name for Markdown imports, record position for JSON and CSV.
+    pub source: String,
+    pub message: String,
+}
+
+/// Result of an import. An entry that fails does not stop the import.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub created: u32,
+    pub updated: u32,
+    /// Entries whose note already matched the database.
+    pub unchanged: u32,
+    pub errors: Vec<ImportError>,
+}
+
+/// Result of `delete_note` and `purge_note`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DeleteResult {
+    pub id: i64,
+    /// Rows the statement changed, as reported by the server. Zero if the
+    /// note did not exist or was not in the expected state.
+    pub rows_affected: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum BatchItemStatus {
+    /// Written and committed.
+    Ok,
+    /// This item failed, so the whole batch was rolled back.
+    Failed,
+    /// Not written because another item failed.
+    RolledBack,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    pub status: BatchItemStatus,
+    /// The note the item refers to. For creates, only set once committed.
+    pub id: Option<i64>,
+    /// The note as written, for creates and updates that were committed.
+    pub note: Option<Note>,
+    pub error: Option<AppError>,
+}
+
+/// Result of a batch command. Batches are all-or-nothing: either every item
+/// is `Ok`, or at least one is `Failed` and the rest are `RolledBack`. Items are in
+/// the order they were sent.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub committed: bool,
+    pub items: Vec<BatchItemResult>,
+}
+
+impl BatchResult {
+    /// Every item was written. Each entry is the item's note id and, for
+    /// creates and updates, the note as stored.
+    pub fn committed(items: Vec<(i64, Option<Note>)>) -> Self {
+        Self {
+            committed: true,
+            items: items.into_iter()
+                .map(|(id, note)| BatchItemResult {
+                    status: BatchItemStatus::Ok,
+                    id: Some(id),
+                    note,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The item at `failed` stopped the batch. `ids` holds each item's note
+    /// id where known.
+    pub fn rolled_back(ids: Vec<Option<i64>>, failed: usize, error: AppError) -> Self {
+        Self::failed(ids, vec![(failed, error)])
+    }
+
+    /// Items rejected before anything was written, e.g. by validation.
+    pub fn failed(ids: Vec<Option<i64>>, errors: Vec<(usize, AppError)>) -> Self {
+        let mut items: Vec<BatchItemResult> = ids.into_iter()
+            .map(|id| BatchItemResult {
+                status: BatchItemStatus::RolledBack,
+                id,
+                note: None,
+                error: None,
+            })
+            .collect();
+        for (index, error) in errors {
+            if let Some(item) = items.get_mut(index) {
+                item.status = BatchItemStatus::Failed;
+                item.error = Some(error);
+            }
+        }
+        Self { committed: false, items }
+    }
+}
+
+fn timestamp(row: &Row, index: usize) -> Option<DateTime<Utc>> {
+    row.get::<Option<Value>, _>(index)
+        .flatten()
+        .and_then(timestamps::from_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(result: &BatchResult) -> Vec<BatchItemStatus> {
+        result.items.iter().map(|item| item.status).collect()
+    }
+
+    #[test]
+    fn a_rolled_back_batch_blames_one_item() {
+        let result = BatchResult::rolled_back(vec![Some(1), None, Some(3)], 1, AppError::not_found("Note"));
+        assert!(!result.committed);
+        assert_eq!(statuses(&result), [BatchItemStatus::RolledBack, BatchItemStatus::Failed, BatchItemStatus::RolledBack]);
+        assert_eq!(result.items.iter().map(|item| item.id).collect::<Vec<_>>(), [Some(1), None, Some(3)]);
+        assert!(matches!(result.items[1].error, Some(AppError::NotFound(_))));
+        assert!(result.items[0].error.is_none() && result.items[2].error.is_none());
+        assert!(result.items.iter().all(|item| item.note.is_none()));
+    }
+
+    #[test]
+    fn a_failed_batch_marks_every_rejected_item() {
+        let result = BatchResult::failed(
+            vec![None, None, None],
+            vec![(0, AppError::validation("a")), (2, AppError::validation("c")), (9, AppError::validation("ignored"))],
+        );
+        assert!(!result.committed);
+        assert_eq!(statuses(&result), [BatchItemStatus::Failed, BatchItemStatus::RolledBack, BatchItemStatus::Failed]);
+        assert_eq!(result.items[2].error.as_ref().map(|e| e.to_string()), Some("c".to_string()));
+    }
+
+    #[test]
+    fn a_committed_batch_is_all_ok() {
+        let result = BatchResult::committed(vec![(5, None), (6, None)]);
+        assert!(result.committed);
+        assert_eq!(statuses(&result), [BatchItemStatus::Ok, BatchItemStatus::Ok]);
+        assert_eq!(result.items[1].id, Some(6));
+    }
+}
@@ -0,0 +1,188 @@
+// src/render.rs
+
+//! Markdown rendering for note content, done here so every view shows a
+//! note the same way. The HTML is sanitized, so raw HTML in a note cannot
+//! run script or restyle the app.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use crate::models::{RenderedNote, TocEntry};
+
+/// Heading ids are prefixed so they cannot clash with ids used by the app.
+const ANCHOR_PREFIX: &str = "note-";
+
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    builder
+        // Task list checkboxes. Raw `<input>`s are forced into the same
+        // read-only checkbox.
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("th", ["style"])
+        .add_tag_attributes("td", ["style"])
+        .add_generic_attributes(["id"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            // Fenced code block languages, for highlighting.
+            ("code", "class") => value.starts_with("language-").then_some(value.into()),
+            // Table column alignment.
+            ("th" | "td", "style") => matches!(
+                value,
+                "text-align: left" | "text-align: center" | "text-align: right"
+            ).then_some(value.into()),
+            (_, "id") => value.starts_with(ANCHOR_PREFIX).then_some(value.into()),
+            _ => Some(value.into()),
+        });
+    builder
+});
+
+/// Renders Markdown with tables, task lists and strikethrough. Every
+/// heading gets an `id` and an entry in the table of contents.
+pub fn render_markdown(content: &str) -> RenderedNote {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let mut events: Vec<Event> = Parser::new_ext(content, options).collect();
+    let toc = anchor_headings(&mut events);
+
+    let mut unsafe_html = String::with_capacity(content.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, events.into_iter());
+
+    RenderedNote {
+        html: SANITIZER.clean(&unsafe_html).to_string(),
+        toc,
+    }
+}
+
+/// Gives every heading a unique id made from its text, replacing any
+/// `{#id}` it was written with, and returns the headings in order.
+fn anchor_headings(events: &mut [Event]) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    let mut i = 0;
+    while i < events.len() {
+        let Event::Start(Tag::Heading { level, .. }) = &events[i] else {
+            i += 1;
+            continue;
+        };
+        let level = *level;
+        let start = i;
+
+        let mut text = String::new();
+        i += 1;
+        while i < events.len() && !matches!(events[i], Event::End(TagEnd::Heading(_))) {
+            if let Event::Text(t) | Event::Code(t) = &events[i] {
+                text.push_str(t);
+            }
+            i += 1;
+        }
+
+        let anchor = unique_anchor(&slugify(&text), &mut used, &mut counts);
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+            *id = Some(CowStr::from(anchor.clone()));
+        }
+        toc.push(TocEntry {
+            level: heading_level(level),
+            text: text.trim().to_string(),
+            anchor,
+        });
+    }
+    toc
+}
+
+fn unique_anchor(slug: &str, used: &mut HashSet<String>, counts: &mut HashMap<String, usize>) -> String {
+    let base = if slug.is_empty() { "section" } else { slug };
+    let count = counts.entry(base.to_string()).or_insert(0);
+
+    loop {
+        let anchor = match *count {
+            0 => format!("{}{}", ANCHOR_PREFIX, base),
+            n => format!("{}{}-{}", ANCHOR_PREFIX, base, n),
+        };
+        *count += 1;
+        if used.insert(anchor.clone()) {
+            return anchor;
+        }
+    }
+}
+
+/// Lowercase words joined by `-`, keeping letters and digits of any script.
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_script_and_event_handlers() {
+        let html = render_markdown(concat!(
+            "<script>alert(1)</script>\n\n",
+            "<img src=\"x.png\" onerror=\"alert(2)\">\n\n",
+            "[click](javascript:alert(3))\n\n",
+            "<iframe src=\"https://example.com\"></iframe>\n\n",
+            "<a href=\"JaVaScRiPt:alert(4)\">raw</a>\n",
+        )).html;
+        let lower = html.to_lowercase();
+        for forbidden in ["<script", "alert(1)", "onerror", "javascript:", "<iframe"] {
+            assert!(!lower.contains(forbidden), "{:?} left in {}", forbidden, html);
+        }
+        assert!(html.contains("<img src=\"x.png\""));
+        assert!(html.contains(">click</a>"));
+    }
+
+    #[test]
+    fn keeps_only_prefixed_ids() {
+        let html = render_markdown("<p id=\"app\">a</p>\n\n<p id=\"note-x\">b</p>\n").html;
+        assert!(!html.contains("id=\"app\""));
+        assert!(html.contains("id=\"note-x\""));
+    }
+
+    #[test]
+    fn heading_anchors_are_unique_and_match_the_toc() {
+        let rendered = render_markdown(concat!(
+            "# Intro\n\n",
+            "## Intro\n\n",
+            "## Intro-1 {#custom}\n\n",
+            "### `code` & Ünïcode\n\n",
+            "## !!!\n",
+        ));
+        let anchors: Vec<_> = rendered.toc.iter().map(|e| e.anchor.as_str()).collect();
+        assert_eq!(
+            anchors,
+            ["note-intro", "note-intro-1", "note-intro-1-1", "note-code-ünïcode", "note-section"],
+        );
+        for entry in &rendered.toc {
+            assert!(
+                rendered.html.contains(&format!("id=\"{}\"", entry.anchor)),
+                "no heading with id {} in {}",
+                entry.anchor,
+                rendered.html,
+            );
+        }
+        assert_eq!(rendered.toc.iter().map(|e| e.level).collect::<Vec<_>>(), [1, 2, 2, 3, 2]);
+        assert_eq!(rendered.toc[3].text, "code & Ünïcode");
+    }
+}
@@ -0,0 +1,166 @@
+// src/search.rs
+
+use crate::models::SearchMode;
+
+const SNIPPET_CHARS: usize = 160;
+const BOOLEAN_OPERATORS: &[char] = &['+', '-', '~', '<', '>', '(', ')', '"', '*', '@'];
+
+/// Extracts the words worth highlighting from a search query. In boolean
+/// mode, excluded terms (`-word`) are dropped and operators are stripped.
+pub fn search_terms(query: &str, mode: SearchMode) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+
+    for raw in query.split_whitespace() {
+        if mode == SearchMode::Boolean && raw.starts_with('-') {
+            continue;
+        }
+
+        let term = match mode {
+            SearchMode::Boolean => raw.trim_matches(BOOLEAN_OPERATORS),
+            SearchMode::NaturalLanguage => raw.trim_matches(|c: char| !c.is_alphanumeric()),
+        };
+        let term = term.to_lowercase();
+
+        if !term.is_empty() && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+
+    // Longest first so "notebook" wins over "note" at the same position.
+    terms.sort_by_key(|t| std::cmp::Reverse(t.chars().count()));
+    terms
+}
+
+/// HTML-escapes `text` and wraps every case-insensitive occurrence of a
+/// term in `<mark>`.
+pub fn highlight(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lowered: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+    let terms: Vec<Vec<char>> = terms.iter().map(|t| t.chars().map(fold).collect()).collect();
+
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let matched = terms.iter()
+            .find(|term| !term.is_empty() && lowered[i..].starts_with(term))
+            .map(|term| term.len());
+
+        match matched {
+            Some(len) => {
+                out.push_str("<mark>");
+                chars[i..i + len].iter().for_each(|c| push_escaped(&mut out, *c));
+                out.push_str("</mark>");
+                i += len;
+            },
+            None => {
+                push_escaped(&mut out, chars[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Returns a highlighted excerpt of `text` centred on the first matching
+/// term, or the start of the text when nothing matches.
+pub fn snippet(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= SNIPPET_CHARS {
+        return highlight(text, terms);
+    }
+
+    let lowered: Vec<char> = chars.iter().map(|c| fold(*c)).collect();
+    let first_match = terms.iter()
+        .filter_map(|term| {
+            let term: Vec<char> = term.chars().map(fold).collect();
+            if term.is_empty() {
+                return None;
+            }
+            lowered.windows(term.len()).position(|w| w == term.as_slice())
+        })
+        .min()
+        .unwrap_or(0);
+
+    let start = first_match.saturating_sub(SNIPPET_CHARS / 3);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let start = end.saturating_sub(SNIPPET_CHARS);
+
+    let excerpt: String = chars[start..end].iter().collect();
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.push_str(&highlight(&excerpt, terms));
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+// Single-char case folding keeps indices in `chars` and `lowered` aligned.
+fn fold(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#39;"),
+        _ => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        search_terms(query, SearchMode::Boolean)
+    }
+
+    #[test]
+    fn boolean_terms_drop_exclusions_and_operators() {
+        assert_eq!(terms("+Rust -java \"note*\" rust"), ["rust", "note"]);
+        assert_eq!(search_terms("note, notebook!", SearchMode::NaturalLanguage), ["notebook", "note"]);
+    }
+
+    #[test]
+    fn highlights_case_insensitively_and_escapes_html() {
+        assert_eq!(
+            highlight("A <Note> & a notebook", &terms("note notebook")),
+            "A &lt;<mark>Note</mark>&gt; &amp; a <mark>notebook</mark>",
+        );
+        assert_eq!(highlight("ÉTÉ été", &terms("été")), "<mark>ÉTÉ</mark> <mark>été</mark>");
+        assert_eq!(highlight("it's", &[]), "it&#39;s");
+    }
+
+    #[test]
+    fn short_text_is_highlighted_whole() {
+        assert_eq!(snippet("find me", &terms("me")), "find <mark>me</mark>");
+    }
+
+    #[test]
+    fn snippets_centre_on_the_first_match() {
+        let text = format!("{}needle{}", "a ".repeat(200), " b".repeat(200));
+        let out = snippet(&text, &terms("needle"));
+        assert!(out.starts_with('…') && out.ends_with('…'));
+        assert!(out.contains("<mark>needle</mark>"));
+        let visible = out.replace("<mark>", "").replace("</mark>", "");
+        assert_eq!(visible.chars().count(), SNIPPET_CHARS + 2);
+    }
+
+    #[test]
+    fn snippets_without_a_match_show_the_start() {
+        let text = "x".repeat(SNIPPET_CHARS * 2);
+        let out = snippet(&text, &terms("missing"));
+        assert!(!out.starts_with('…'));
+        assert_eq!(out, format!("{}…", "x".repeat(SNIPPET_CHARS)));
+    }
+}
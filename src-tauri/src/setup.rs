@@ -0,0 +1,713 @@
+// src/setup.rs
+
+//! First-run system setup: get Docker running and start the MySQL
+//! container.
+//!
+//! Each platform describes its setup as an ordered list of steps
+//! (`SystemSetup`); `run_setup` runs them and reports progress as
+//! `installation-stage` events carrying an `InstallationStage`, the same on
+//! every OS.
+//!
+//! Progress is kept in `setup-state.json` in the app data dir, so a setup
+//! that was interrupted resumes where it stopped instead of redoing steps
+//! such as the Docker install.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Listener, Manager};
+use tokio::sync::{oneshot, Mutex};
+use crate::db::{backup, DatabaseState};
+
+/// The MySQL container setup starts. Backups run their tools inside it.
+pub const MYSQL_CONTAINER: &str = "docker-mysql-1";
+const STATE_FILE: &str = "setup-state.json";
+/// How long the user has to answer a sudo password prompt.
+const SUDO_PASSWORD_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long the user has to read and answer a confirmation.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Where setup is, as shown by the setup dialogs. Serialized as the bare
+/// variant name, e.g. `"DockerInstalling"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstallationStage {
+    NotStarted,
+    CheckingDocker,
+    DockerNotInstalled,
+    DockerInstalling,
+    DockerInstallFailed,
+    DockerInstalled,
+    PreparingMySQLContainer,
+    StartingMySQLContainer,
+    MySQLContainerStarted,
+    MySQLSetupFailed,
+    SetupComplete,
+}
+
+/// Receives stage changes while setup runs.
+pub trait SetupProgress: Send + Sync {
+    fn stage(&self, stage: InstallationStage);
+}
+
+impl SetupProgress for tauri::AppHandle {
+    fn stage(&self, stage: InstallationStage) {
+        if let Err(e) = self.emit("installation-stage", stage) {
+            log::warn!("Failed to emit installation stage {:?}: {}", stage, e);
+        }
+    }
+}
+
+/// The logs the setup dialogs show while a step runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupLog {
+    DockerInstall,
+    MySqlContainer,
+}
+
+impl SetupLog {
+    fn event(self) -> &'static str {
+        match self {
+            SetupLog::DockerInstall => "docker-install-log",
+            SetupLog::MySqlContainer => "mysql-container-log",
+        }
+    }
+}
+
+/// How to install Docker when setup finds it missing or incomplete.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DockerInstallMode {
+    /// Installs only what is missing, keeping existing Docker, Podman and
+    /// containerd installs and their data.
+    #[default]
+    Repair,
+    /// Backs up the existing configuration, removes Docker and its data,
+    /// then installs from scratch. Asks the user to confirm first.
+    CleanReinstall,
+}
+
+/// A question for the user before setup does something that cannot be
+/// undone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfirmRequest {
+    pub message: String,
+    /// What the action affects, one item per line.
+    pub details: Vec<String>,
+}
+
+/// What setup steps need from the app, besides running commands.
+#[async_trait]
+pub trait SetupHost: Send + Sync {
+    /// Where `.env` and the generated Docker files live.
+    fn data_dir(&self) -> Result<PathBuf>;
+
+    fn log(&self, log: SetupLog, message: &str);
+
+    /// Asks the user for their sudo password.
+    async fn sudo_password(&self) -> Result<String>;
+
+    /// Asks the user to confirm `request`. `false` means they declined.
+    async fn confirm(&self, request: &ConfirmRequest) -> Result<bool>;
+
+    /// Backs up the app's database, returning the backup file, or `None`
+    /// when MySQL is not reachable and there is nothing to back up from.
+    async fn back_up_database(&self) -> Result<Option<PathBuf>>;
+}
+
+#[derive(Serialize, Clone)]
+struct SudoPasswordRequest {
+    request_id: String,
+}
+
+#[derive(Serialize, Clone)]
+struct SetupConfirmRequest<'a> {
+    request_id: String,
+    #[serde(flatten)]
+    request: &'a ConfirmRequest,
+}
+
+#[async_trait]
+impl SetupHost for tauri::AppHandle {
+    fn data_dir(&self) -> Result<PathBuf> {
+        Ok(self.path().local_data_dir()?)
+    }
+
+    fn log(&self, log: SetupLog, message: &str) {
+        if let Err(e) = self.emit(log.event(), message) {
+            log::warn!("Failed to emit {}: {}", log.event(), e);
+        }
+    }
+
+    /// Emits `sudo-password-request` and waits for the frontend to answer
+    /// on `sudo-password-response-<request_id>`.
+    async fn sudo_password(&self) -> Result<String> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let request = SudoPasswordRequest { request_id: request_id.clone() };
+
+        ask_frontend(self, "sudo-password", &request_id, request, SUDO_PASSWORD_TIMEOUT)
+            .await
+            .map_err(|e| anyhow!("Sudo password prompt: {}", e))
+    }
+
+    /// Emits `setup-confirm-request` and waits for the frontend to answer
+    /// `true` or `false` on `setup-confirm-response-<request_id>`.
+    async fn confirm(&self, request: &ConfirmRequest) -> Result<bool> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let payload = SetupConfirmRequest { request_id: request_id.clone(), request };
+
+        let answer = ask_frontend(self, "setup-confirm", &request_id, payload, CONFIRM_TIMEOUT)
+            .await
+            .map_err(|e| anyhow!("Confirmation prompt: {}", e))?;
+        Ok(serde_json::from_str(&answer).unwrap_or(false))
+    }
+
+    async fn back_up_database(&self) -> Result<Option<PathBuf>> {
+        if self.state::<DatabaseState>().reachable_pool().await.is_none() {
+            return Ok(None);
+        }
+        let backup = backup::backup_database(self).await?;
+        Ok(Some(backup::backup_dir(self)?.join(backup.file_name)))
+    }
+}
+
+/// Emits `<name>-request` with `payload` and returns the payload of the
+/// first `<name>-response-<request_id>`.
+async fn ask_frontend<P: Serialize + Clone>(
+    app: &tauri::AppHandle,
+    name: &str,
+    request_id: &str,
+    payload: P,
+    timeout: Duration,
+) -> Result<String> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Arc::new(Mutex::new(Some(tx)));
+
+    let handler = app.listen(format!("{}-response-{}", name, request_id), move |event| {
+        let tx = tx.clone();
+        let answer = event.payload().to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Some(tx) = tx.lock().await.take() {
+                let _ = tx.send(answer);
+            }
+        });
+    });
+
+    let answer = match app.emit(&format!("{}-request", name), payload) {
+        Ok(()) => tokio::time::timeout(timeout, rx)
+            .await
+            .map_err(|_| anyhow!("timed out waiting for an answer"))
+            .and_then(|received| received.map_err(|_| anyhow!("closed without an answer"))),
+        Err(e) => Err(anyhow!("failed to emit request: {}", e)),
+    };
+    app.unlisten(handler);
+
+    answer
+}
+
+/// One unit of setup work. The orchestrator reports `started` before
+/// `run`, then `completed` or `failed`; steps report any stages in between
+/// themselves.
+#[async_trait]
+pub trait SetupStep: Send + Sync {
+    /// Short name used in logs and error messages.
+    fn name(&self) -> &'static str;
+
+    fn started(&self) -> Option<InstallationStage> {
+        None
+    }
+
+    fn completed(&self) -> Option<InstallationStage> {
+        None
+    }
+
+    fn failed(&self) -> Option<InstallationStage> {
+        None
+    }
+
+    /// Whether a completed run holds for later setups, so the step is
+    /// skipped once it has completed. Checks and anything that must hold
+    /// on every launch keep the default.
+    fn skip_when_completed(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, progress: &dyn SetupProgress) -> Result<()>;
+}
+
+/// The setup of one platform.
+pub trait SystemSetup: Send + Sync {
+    /// Steps in the order they must run.
+    fn steps(&self) -> Vec<Box<dyn SetupStep>>;
+}
+
+/// How a step's latest run ended. A step left `Started` was interrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepOutcome {
+    Started,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What the last setup run got to, as stored in the state file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetupStatus {
+    pub stage: InstallationStage,
+    /// The latest record of every step that has run, in the order they
+    /// first ran.
+    pub steps: Vec<StepRecord>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for SetupStatus {
+    fn default() -> Self {
+        Self { stage: InstallationStage::NotStarted, steps: Vec::new(), updated_at: None }
+    }
+}
+
+impl SetupStatus {
+    fn completed(&self, step: &str) -> bool {
+        self.steps.iter().any(|r| r.name == step && r.outcome == StepOutcome::Completed)
+    }
+}
+
+/// The setup state file. Every change is written straight away, so the
+/// file is current however the app exits.
+pub struct SetupState {
+    path: PathBuf,
+    status: std::sync::Mutex<SetupStatus>,
+}
+
+impl SetupState {
+    /// Reads the state at `path`. A missing or unreadable file is a setup
+    /// that has not started.
+    pub fn load(path: PathBuf) -> Self {
+        let status = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable setup state {}: {}", path.display(), e);
+                SetupStatus::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SetupStatus::default(),
+            Err(e) => {
+                log::warn!("Ignoring unreadable setup state {}: {}", path.display(), e);
+                SetupStatus::default()
+            }
+        };
+        Self { path, status: std::sync::Mutex::new(status) }
+    }
+
+    pub fn status(&self) -> SetupStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn completed(&self, step: &str) -> bool {
+        self.status.lock().unwrap().completed(step)
+    }
+
+    fn record_stage(&self, stage: InstallationStage) {
+        self.update(|status| status.stage = stage);
+    }
+
+    fn record_step(&self, name: &str, outcome: StepOutcome, error: Option<String>) {
+        self.update(|status| {
+            let record = StepRecord { name: name.to_string(), outcome, error, updated_at: Utc::now() };
+            match status.steps.iter_mut().find(|r| r.name == name) {
+                Some(existing) => *existing = record,
+                None => status.steps.push(record),
+            }
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SetupStatus)) {
+        let mut status = self.status.lock().unwrap();
+        change(&mut status);
+        status.updated_at = Some(Utc::now());
+        // Losing the file only costs redoing steps next time, so setup
+        // carries on.
+        if let Err(e) = write_state(&self.path, &status) {
+            log::warn!("Failed to save setup state to {}: {:#}", self.path.display(), e);
+        }
+    }
+}
+
+fn write_state(path: &Path, status: &SetupStatus) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Written aside and renamed, so a crash never leaves half a file.
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, serde_json::to_vec_pretty(status)?)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Where the app keeps its setup state.
+pub fn state_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(STATE_FILE))
+}
+
+/// Reports stages and saves them to the state file.
+struct Recorded<'a> {
+    progress: &'a dyn SetupProgress,
+    state: &'a SetupState,
+}
+
+impl SetupProgress for Recorded<'_> {
+    fn stage(&self, stage: InstallationStage) {
+        self.state.record_stage(stage);
+        self.progress.stage(stage);
+    }
+}
+
+/// Runs the steps of `setup` in order, stopping at the first failure.
+/// Steps that `skip_when_completed` and completed in an earlier run are
+/// skipped, reporting their `completed` stage again.
+pub async fn run_setup(setup: &dyn SystemSetup, progress: &dyn SetupProgress, state: &SetupState) -> Result<()> {
+    let progress = Recorded { progress, state };
+    progress.stage(InstallationStage::NotStarted);
+
+    for step in setup.steps() {
+        if step.skip_when_completed() && state.completed(step.name()) {
+            log::info!("Setup step {} already completed, skipping", step.name());
+            if let Some(stage) = step.completed() {
+                progress.stage(stage);
+            }
+            continue;
+        }
+
+        if let Some(stage) = step.started() {
+            progress.stage(stage);
+        }
+
+        log::info!("Setup step {} started", step.name());
+        state.record_step(step.name(), StepOutcome::Started, None);
+        if let Err(e) = step.run(&progress).await {
+            log::warn!("Setup step {} failed: {:#}", step.name(), e);
+            state.record_step(step.name(), StepOutcome::Failed, Some(format!("{:#}", e)));
+            if let Some(stage) = step.failed() {
+                progress.stage(stage);
+            }
+            return Err(e.context(format!("Setup step {} failed", step.name())));
+        }
+        state.record_step(step.name(), StepOutcome::Completed, None);
+
+        if let Some(stage) = step.completed() {
+            progress.stage(stage);
+        }
+    }
+
+    progress.stage(InstallationStage::SetupComplete);
+    Ok(())
+}
+
+/// The setup for the OS the app was built for. `docker_mode` applies
+/// where setup installs Docker itself.
+pub fn platform_setup(app: &tauri::AppHandle, docker_mode: DockerInstallMode) -> Box<dyn SystemSetup> {
+    #[cfg(target_os = "linux")]
+    let setup = crate::ubuntu_setup::UbuntuSystemSetup::new(app.clone(), docker_mode);
+    #[cfg(target_os = "windows")]
+    let setup = {
+        // Windows setup never removes an existing Docker install.
+        let _ = docker_mode;
+        crate::windows_setup::WindowsSystemSetup::new(app.clone())
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    let setup = {
+        let _ = (app, docker_mode);
+        ManualSetup
+    };
+
+    Box::new(setup)
+}
+
+/// Platforms without automated setup, where Docker and MySQL are expected
+/// to be set up by hand.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+struct ManualSetup;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+impl SystemSetup for ManualSetup {
+    fn steps(&self) -> Vec<Box<dyn SetupStep>> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use anyhow::anyhow;
+    use InstallationStage::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        stages: Mutex<Vec<InstallationStage>>,
+    }
+
+    impl Recorder {
+        fn stages(&self) -> Vec<InstallationStage> {
+            self.stages.lock().unwrap().clone()
+        }
+    }
+
+    impl SetupProgress for Recorder {
+        fn stage(&self, stage: InstallationStage) {
+            self.stages.lock().unwrap().push(stage);
+        }
+    }
+
+    struct MockStep {
+        name: &'static str,
+        started: Option<InstallationStage>,
+        completed: Option<InstallationStage>,
+        failed: Option<InstallationStage>,
+        /// Reported from inside `run`.
+        reports: Vec<InstallationStage>,
+        skip_when_completed: bool,
+        fails: bool,
+        ran: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl SetupStep for MockStep {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn started(&self) -> Option<InstallationStage> {
+            self.started
+        }
+
+        fn completed(&self) -> Option<InstallationStage> {
+            self.completed
+        }
+
+        fn failed(&self) -> Option<InstallationStage> {
+            self.failed
+        }
+
+        fn skip_when_completed(&self) -> bool {
+            self.skip_when_completed
+        }
+
+        async fn run(&self, progress: &dyn SetupProgress) -> Result<()> {
+            self.ran.lock().unwrap().push(self.name);
+            for stage in &self.reports {
+                progress.stage(*stage);
+            }
+            if self.fails {
+                Err(anyhow!("{} broke", self.name))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    struct MockSetup {
+        steps: Mutex<Vec<MockStep>>,
+    }
+
+    impl SystemSetup for MockSetup {
+        fn steps(&self) -> Vec<Box<dyn SetupStep>> {
+            self.steps.lock().unwrap()
+                .drain(..)
+                .map(|step| Box::new(step) as Box<dyn SetupStep>)
+                .collect()
+        }
+    }
+
+    /// Steps shaped like the real ones; `failing` names the step that fails.
+    fn docker_and_mysql(failing: Option<&str>, ran: &Arc<Mutex<Vec<&'static str>>>) -> MockSetup {
+        let step = |name, started, completed, failed, reports: Vec<InstallationStage>| MockStep {
+            name,
+            started,
+            completed,
+            failed,
+            reports,
+            // Like the real install, the only step worth remembering.
+            skip_when_completed: name == "install_docker",
+            fails: failing == Some(name),
+            ran: ran.clone(),
+        };
+        MockSetup {
+            steps: Mutex::new(vec![
+                step("check_system", Some(CheckingDocker), None, None, vec![]),
+                step("install_docker", None, Some(DockerInstalled), Some(DockerInstallFailed), vec![DockerNotInstalled, DockerInstalling]),
+                step("start_mysql", Some(StartingMySQLContainer), Some(MySQLContainerStarted), Some(MySQLSetupFailed), vec![]),
+            ]),
+        }
+    }
+
+    /// A state file in a fresh temporary directory.
+    struct TempState {
+        dir: tempfile::TempDir,
+    }
+
+    impl TempState {
+        fn new() -> Self {
+            Self { dir: tempfile::TempDir::new().unwrap() }
+        }
+
+        fn path(&self) -> PathBuf {
+            self.dir.path().join(STATE_FILE)
+        }
+
+        fn load(&self) -> SetupState {
+            SetupState::load(self.path())
+        }
+    }
+
+    fn outcomes(status: &SetupStatus) -> Vec<(&str, StepOutcome)> {
+        status.steps.iter().map(|r| (r.name.as_str(), r.outcome)).collect()
+    }
+
+    #[tokio::test]
+    async fn reports_every_stage_in_order_on_success() {
+        let ran = Arc::default();
+        let recorder = Recorder::default();
+
+        run_setup(&docker_and_mysql(None, &ran), &recorder, &TempState::new().load()).await.unwrap();
+
+        assert_eq!(recorder.stages(), vec![
+            NotStarted,
+            CheckingDocker,
+            DockerNotInstalled,
+            DockerInstalling,
+            DockerInstalled,
+            StartingMySQLContainer,
+            MySQLContainerStarted,
+            SetupComplete,
+        ]);
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system", "install_docker", "start_mysql"]);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_failure_and_reports_its_stage() {
+        let ran = Arc::default();
+        let recorder = Recorder::default();
+
+        let err = run_setup(&docker_and_mysql(Some("install_docker"), &ran), &recorder, &TempState::new().load()).await.unwrap_err();
+
+        assert_eq!(recorder.stages(), vec![
+            NotStarted,
+            CheckingDocker,
+            DockerNotInstalled,
+            DockerInstalling,
+            DockerInstallFailed,
+        ]);
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system", "install_docker"]);
+        assert_eq!(format!("{:#}", err), "Setup step install_docker failed: install_docker broke");
+    }
+
+    #[tokio::test]
+    async fn failure_without_a_failed_stage_reports_nothing_more() {
+        let ran = Arc::default();
+        let recorder = Recorder::default();
+
+        assert!(run_setup(&docker_and_mysql(Some("check_system"), &ran), &recorder, &TempState::new().load()).await.is_err());
+
+        assert_eq!(recorder.stages(), vec![NotStarted, CheckingDocker]);
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system"]);
+    }
+
+    #[tokio::test]
+    async fn setup_without_steps_completes_immediately() {
+        let recorder = Recorder::default();
+        let setup = MockSetup { steps: Mutex::default() };
+
+        run_setup(&setup, &recorder, &TempState::new().load()).await.unwrap();
+
+        assert_eq!(recorder.stages(), vec![NotStarted, SetupComplete]);
+    }
+
+    #[test]
+    fn stages_serialize_as_bare_variant_names() {
+        assert_eq!(serde_json::to_string(&DockerInstalling).unwrap(), "\"DockerInstalling\"");
+        assert_eq!(serde_json::to_string(&MySQLSetupFailed).unwrap(), "\"MySQLSetupFailed\"");
+    }
+
+    #[tokio::test]
+    async fn saves_stage_and_step_outcomes() {
+        let temp = TempState::new();
+        let ran = Arc::default();
+
+        let state = temp.load();
+        assert!(run_setup(&docker_and_mysql(Some("start_mysql"), &ran), &Recorder::default(), &state).await.is_err());
+
+        let saved = temp.load().status();
+        assert_eq!(saved, state.status());
+        assert_eq!(saved.stage, MySQLSetupFailed);
+        assert_eq!(outcomes(&saved), vec![
+            ("check_system", StepOutcome::Completed),
+            ("install_docker", StepOutcome::Completed),
+            ("start_mysql", StepOutcome::Failed),
+        ]);
+        assert_eq!(saved.steps[2].error.as_deref(), Some("start_mysql broke"));
+    }
+
+    #[tokio::test]
+    async fn rerun_skips_completed_steps_that_allow_it() {
+        let temp = TempState::new();
+        run_setup(&docker_and_mysql(Some("start_mysql"), &Arc::default()), &Recorder::default(), &temp.load())
+            .await
+            .unwrap_err();
+
+        let ran = Arc::default();
+        let recorder = Recorder::default();
+        run_setup(&docker_and_mysql(None, &ran), &recorder, &temp.load()).await.unwrap();
+
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system", "start_mysql"]);
+        assert_eq!(recorder.stages(), vec![
+            NotStarted,
+            CheckingDocker,
+            DockerInstalled,
+            StartingMySQLContainer,
+            MySQLContainerStarted,
+            SetupComplete,
+        ]);
+        let saved = temp.load().status();
+        assert_eq!(saved.stage, SetupComplete);
+        assert!(outcomes(&saved).iter().all(|(_, outcome)| *outcome == StepOutcome::Completed));
+    }
+
+    #[tokio::test]
+    async fn rerun_repeats_failed_and_interrupted_steps() {
+        let temp = TempState::new();
+        let state = temp.load();
+        state.record_step("install_docker", StepOutcome::Started, None);
+        state.record_stage(DockerInstalling);
+        assert_eq!(temp.load().status().stage, DockerInstalling);
+
+        let ran = Arc::default();
+        run_setup(&docker_and_mysql(None, &ran), &Recorder::default(), &temp.load()).await.unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system", "install_docker", "start_mysql"]);
+
+        let temp = TempState::new();
+        run_setup(&docker_and_mysql(Some("install_docker"), &Arc::default()), &Recorder::default(), &temp.load())
+            .await
+            .unwrap_err();
+        let ran = Arc::default();
+        run_setup(&docker_and_mysql(None, &ran), &Recorder::default(), &temp.load()).await.unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec!["check_system", "install_docker", "start_mysql"]);
+    }
+
+    #[test]
+    fn missing_or_corrupt_state_is_a_fresh_setup() {
+        let temp = TempState::new();
+        assert_eq!(temp.load().status(), SetupStatus::default());
+
+        std::fs::create_dir_all(temp.dir.path()).unwrap();
+        std::fs::write(temp.path(), "{ not json").unwrap();
+        assert_eq!(temp.load().status(), SetupStatus::default());
+    }
+}
@@ -0,0 +1,240 @@
+// src/store.rs
+
+pub mod sqlite;
+
+use async_trait::async_trait;
+use mysql_async::Pool;
+use serde::Serialize;
+use crate::db::notes::NoteRepository;
+use crate::db::tags::TagRepository;
+use crate::error::AppError;
+use crate::events::NoteEvents;
+use crate::models::{BatchResult, DeleteResult, Note, NoteListQuery, NotePage, SearchQuery, SearchResults, TagUsage};
+
+pub use sqlite::SqliteStore;
+
+/// Which backend is serving note commands.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum StorageBackend {
+    MySql,
+    /// Embedded fallback used until MySQL is reachable.
+    Sqlite,
+}
+
+/// Storage for notes and their tags. Commands go through this trait so they
+/// work the same on MySQL and on the embedded SQLite fallback.
+///
+/// Revision history and schema migrations are MySQL-only and keep using
+/// their repositories directly. Bulk changes made on MySQL (tag renames,
+/// trash expiry) reach the SQLite cache with the next sync.
+#[async_trait]
+pub trait NoteStore: Send + Sync {
+    fn backend(&self) -> StorageBackend;
+
+    async fn create_note(&self, note: &Note) -> Result<Note, AppError>;
+    async fn get_all_notes(&self) -> Result<Vec<Note>, AppError>;
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<NotePage, AppError>;
+    /// Up to `limit` notes not in the trash with an id above `after`, in id
+    /// order, so bulk exports can walk the notes a batch at a time.
+    async fn notes_after(&self, after: i64, limit: usize) -> Result<Vec<Note>, AppError>;
+    /// Notes not in the trash.
+    async fn count_notes(&self) -> Result<u64, AppError>;
+    async fn get_note_by_id(&self, id: i64) -> Result<Note, AppError>;
+    /// Rejects a stale `note.version` with `AppError::Conflict`.
+    async fn update_note(&self, id: i64, note: &Note) -> Result<Note, AppError>;
+    /// Moves the note to the trash.
+    async fn delete_note(&self, id: i64) -> Result<DeleteResult, AppError>;
+    async fn search_notes(&self, query: &SearchQuery) -> Result<SearchResults, AppError>;
+    async fn get_notes_by_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<Note>, AppError>;
+
+    /// Batches run in one transaction and are all-or-nothing. Item failures
+    /// are reported in the `BatchResult`, not as an `Err`.
+    async fn batch_create_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError>;
+    /// Like `update_note` for each note, also replacing its tags.
+    async fn batch_update_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError>;
+    async fn batch_delete_notes(&self, ids: &[i64]) -> Result<BatchResult, AppError>;
+
+    async fn list_trash(&self) -> Result<Vec<Note>, AppError>;
+    async fn restore_note(&self, id: i64) -> Result<Note, AppError>;
+    async fn purge_note(&self, id: i64) -> Result<DeleteResult, AppError>;
+    async fn purge_expired_trash(&self, retention_days: u32) -> Result<u64, AppError>;
+
+    async fn add_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError>;
+    async fn remove_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError>;
+    async fn rename_tag(&self, from: &str, to: &str) -> Result<(), AppError>;
+    async fn list_tags(&self) -> Result<Vec<TagUsage>, AppError>;
+}
+
+/// `NoteStore` backed by the MySQL pool. Writes are copied into the SQLite
+/// cache so the notes stay readable if MySQL goes away.
+pub struct MySqlStore {
+    notes: NoteRepository,
+    tags: TagRepository,
+    cache: SqliteStore,
+    events: NoteEvents,
+}
+
+impl MySqlStore {
+    pub fn new(pool: Pool, cache: SqliteStore, events: NoteEvents) -> Self {
+        Self {
+            notes: NoteRepository::new(pool.clone()).with_events(events.clone()),
+            tags: TagRepository::new(pool),
+            cache,
+            events,
+        }
+    }
+
+    /// Copies MySQL's current state of a note into the cache and returns
+    /// it. Failures only leave the cache stale until the next sync, so they
+    /// are logged.
+    async fn refresh_cached(&self, id: i64) -> Option<Note> {
+        let (note, result) = match self.notes.find_note(id).await {
+            Ok(Some(note)) => {
+                let result = self.cache.mirror_note(&note).await;
+                (Some(note), result)
+            },
+            Ok(None) => (None, self.cache.forget_note(id).await),
+            Err(e) => (None, Err(e)),
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to update cached copy of note {}: {}", id, e);
+        }
+        note
+    }
+
+    async fn cache_note(&self, note: &Note) {
+        if let Err(e) = self.cache.mirror_note(note).await {
+            log::warn!("Failed to cache note {:?}: {}", note.id, e);
+        }
+    }
+
+    async fn cache_batch(&self, result: &BatchResult) {
+        if !result.committed {
+            return;
+        }
+        for item in &result.items {
+            match (&item.note, item.id) {
+                (Some(note), _) => self.cache_note(note).await,
+                (None, Some(id)) => {
+                    self.refresh_cached(id).await;
+                },
+                (None, None) => {},
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NoteStore for MySqlStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::MySql
+    }
+
+    async fn create_note(&self, note: &Note) -> Result<Note, AppError> {
+        let created = self.notes.create_note(note).await?;
+        self.cache_note(&created).await;
+        Ok(created)
+    }
+
+    async fn get_all_notes(&self) -> Result<Vec<Note>, AppError> {
+        self.notes.get_all_notes().await
+    }
+
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<NotePage, AppError> {
+        self.notes.list_notes(query).await
+    }
+
+    async fn notes_after(&self, after: i64, limit: usize) -> Result<Vec<Note>, AppError> {
+        self.notes.notes_after(after, limit).await
+    }
+
+    async fn count_notes(&self) -> Result<u64, AppError> {
+        self.notes.count_notes().await
+    }
+
+    async fn get_note_by_id(&self, id: i64) -> Result<Note, AppError> {
+        self.notes.get_note_by_id(id).await
+    }
+
+    async fn update_note(&self, id: i64, note: &Note) -> Result<Note, AppError> {
+        let updated = self.notes.update_note(id, note).await?;
+        self.cache_note(&updated).await;
+        Ok(updated)
+    }
+
+    async fn delete_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let deleted = self.notes.delete_note(id).await?;
+        self.refresh_cached(id).await;
+        Ok(deleted)
+    }
+
+    async fn search_notes(&self, query: &SearchQuery) -> Result<SearchResults, AppError> {
+        self.notes.search_notes(query).await
+    }
+
+    async fn get_notes_by_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<Note>, AppError> {
+        self.notes.get_notes_by_tags(tags, match_all).await
+    }
+
+    async fn batch_create_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let result = self.notes.batch_create_notes(notes).await?;
+        self.cache_batch(&result).await;
+        Ok(result)
+    }
+
+    async fn batch_update_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let result = self.notes.batch_update_notes(notes).await?;
+        self.cache_batch(&result).await;
+        Ok(result)
+    }
+
+    async fn batch_delete_notes(&self, ids: &[i64]) -> Result<BatchResult, AppError> {
+        let result = self.notes.batch_delete_notes(ids).await?;
+        self.cache_batch(&result).await;
+        Ok(result)
+    }
+
+    async fn list_trash(&self) -> Result<Vec<Note>, AppError> {
+        self.notes.list_trash().await
+    }
+
+    async fn restore_note(&self, id: i64) -> Result<Note, AppError> {
+        let restored = self.notes.restore_note(id).await?;
+        self.cache_note(&restored).await;
+        Ok(restored)
+    }
+
+    async fn purge_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let purged = self.notes.purge_note(id).await?;
+        self.refresh_cached(id).await;
+        Ok(purged)
+    }
+
+    async fn purge_expired_trash(&self, retention_days: u32) -> Result<u64, AppError> {
+        self.notes.purge_expired_trash(retention_days).await
+    }
+
+    async fn add_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let current = self.tags.add_tags(note_id, tags).await?;
+        if let Some(note) = self.refresh_cached(note_id).await {
+            self.events.updated(&note);
+        }
+        Ok(current)
+    }
+
+    async fn remove_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let current = self.tags.remove_tags(note_id, tags).await?;
+        if let Some(note) = self.refresh_cached(note_id).await {
+            self.events.updated(&note);
+        }
+        Ok(current)
+    }
+
+    async fn rename_tag(&self, from: &str, to: &str) -> Result<(), AppError> {
+        self.tags.rename_tag(from, to).await
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagUsage>, AppError> {
+        self.tags.list_tags().await
+    }
+}
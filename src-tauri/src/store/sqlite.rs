@@ -0,0 +1,758 @@
+// src/store/sqlite.rs
+
+mod outbox;
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::{params, types::Value, Connection, OptionalExtension, Row, ToSql};
+use crate::db::notes::{NoteCursor, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::db::tags::normalize_tags;
+use crate::error::AppError;
+use crate::events::{NoteEvents, NOTE_CREATED, NOTE_DELETED, NOTE_UPDATED};
+use crate::models::{
+    BatchResult, DeleteResult, Note, NoteListQuery, NotePage, SearchHit, SearchQuery, SearchResults, SortDirection, TagUsage,
+};
+use crate::search;
+use super::{NoteStore, StorageBackend};
+
+pub use outbox::{OutboxEntry, OutboxOp};
+use outbox::enqueue;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL COLLATE NOCASE,
+    content TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    deleted_at TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX IF NOT EXISTS idx_notes_created_at ON notes (created_at, id);
+CREATE INDEX IF NOT EXISTS idx_notes_updated_at ON notes (updated_at, id);
+CREATE INDEX IF NOT EXISTS idx_notes_title ON notes (title, id);
+CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes (deleted_at);
+
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE IF NOT EXISTS note_tags (
+    note_id INTEGER NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (note_id, tag_id)
+);
+CREATE INDEX IF NOT EXISTS idx_note_tags_tag ON note_tags (tag_id);
+"#;
+
+const NOTE_COLUMNS: &str = "id, title, content, created_at, updated_at, deleted_at, version";
+
+/// Embedded `NoteStore` used while MySQL is not available, e.g. on first
+/// launch before Docker setup has run or while the container is stopped.
+///
+/// It doubles as a cache of the MySQL notes: mirrored rows keep their MySQL
+/// id and version, notes created here get negative ids until they are
+/// synced, and every write is recorded in an outbox for `db::sync` to
+/// replay (see `outbox`).
+///
+/// Timestamps are stored as fixed-width RFC 3339 UTC strings, so comparing
+/// them as text orders them correctly. Search is a plain substring match
+/// rather than MySQL's full-text index.
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+    events: NoteEvents,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    fn init(mut conn: Connection) -> Result<Self, AppError> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        outbox::migrate(&mut conn)?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)), events: NoteEvents::default() })
+    }
+
+    /// Publishes every change made through the `NoteStore` methods.
+    /// Mirroring MySQL's notes into the cache publishes nothing.
+    pub fn with_events(mut self, events: NoteEvents) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Runs blocking SQLite work off the async runtime.
+    async fn run<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, AppError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock()
+                .map_err(|_| AppError::Internal("SQLite connection lock poisoned".into()))?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+    }
+}
+
+#[async_trait]
+impl NoteStore for SqliteStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    async fn create_note(&self, note: &Note) -> Result<Note, AppError> {
+        let note = note.clone();
+        let created = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let id = insert_note(&tx, &note)?;
+            tx.commit()?;
+
+            get_note(conn, id)
+        }).await?;
+        self.events.created(&created);
+        Ok(created)
+    }
+
+    async fn get_all_notes(&self) -> Result<Vec<Note>, AppError> {
+        self.run(|conn| {
+            query_notes(conn, &format!(
+                "SELECT {NOTE_COLUMNS} FROM notes WHERE deleted_at IS NULL ORDER BY created_at DESC"
+            ), &[])
+        }).await
+    }
+
+    async fn notes_after(&self, after: i64, limit: usize) -> Result<Vec<Note>, AppError> {
+        self.run(move |conn| {
+            query_notes(conn, &format!(
+                "SELECT {NOTE_COLUMNS} FROM notes WHERE deleted_at IS NULL AND id > ?1 ORDER BY id LIMIT ?2"
+            ), &[&after, &(limit as i64)])
+        }).await
+    }
+
+    async fn count_notes(&self) -> Result<u64, AppError> {
+        self.run(|conn| {
+            Ok(conn.query_row("SELECT COUNT(*) FROM notes WHERE deleted_at IS NULL", [], |row| row.get(0))?)
+        }).await
+    }
+
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<NotePage, AppError> {
+        let query = query.clone();
+        self.run(move |conn| list_notes(conn, &query)).await
+    }
+
+    async fn get_note_by_id(&self, id: i64) -> Result<Note, AppError> {
+        self.run(move |conn| get_note(conn, id)).await
+    }
+
+    async fn update_note(&self, id: i64, note: &Note) -> Result<Note, AppError> {
+        let note = note.clone();
+        let updated = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            write_update(&tx, id, &note)?;
+            tx.commit()?;
+
+            get_note(conn, id)
+        }).await?;
+        self.events.updated(&updated);
+        Ok(updated)
+    }
+
+    async fn delete_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        let (deleted, note) = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let rows_affected = trash_note(&tx, id)?;
+            tx.commit()?;
+
+            let note = if rows_affected > 0 { get_note_any(conn, id)? } else { None };
+            Ok((DeleteResult { id, rows_affected }, note))
+        }).await?;
+        if let Some(note) = note {
+            self.events.deleted(&note);
+        }
+        Ok(deleted)
+    }
+
+    async fn search_notes(&self, query: &SearchQuery) -> Result<SearchResults, AppError> {
+        let query = query.clone();
+        self.run(move |conn| search_notes(conn, &query)).await
+    }
+
+    async fn get_notes_by_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<Note>, AppError> {
+        let tag_names = normalize_tags(tags);
+        self.run(move |conn| {
+            if tag_names.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let placeholders = vec!["?"; tag_names.len()].join(", ");
+            let having = if match_all {
+                format!("HAVING COUNT(DISTINCT nt.tag_id) = {}", tag_names.len())
+            } else {
+                String::new()
+            };
+            let sql = format!(
+                "SELECT {NOTE_COLUMNS} FROM notes \
+                 WHERE deleted_at IS NULL AND id IN ( \
+                     SELECT nt.note_id FROM note_tags nt \
+                     JOIN tags t ON t.id = nt.tag_id \
+                     WHERE t.name IN ({placeholders}) \
+                     GROUP BY nt.note_id \
+                     {having} \
+                 ) \
+                 ORDER BY created_at DESC"
+            );
+            let params: Vec<&dyn ToSql> = tag_names.iter().map(|t| t as &dyn ToSql).collect();
+            query_notes(conn, &sql, &params)
+        }).await
+    }
+
+    async fn batch_create_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let notes = notes.to_vec();
+        let result = self.run(move |conn| {
+            // Dropping the transaction without committing rolls it back.
+            let tx = conn.transaction()?;
+            let mut ids = Vec::with_capacity(notes.len());
+            for (index, note) in notes.iter().enumerate() {
+                match insert_note(&tx, note) {
+                    Ok(id) => ids.push(id),
+                    Err(e) => return Ok(BatchResult::rolled_back(vec![None; notes.len()], index, e)),
+                }
+            }
+            tx.commit()?;
+
+            batch_written(conn, &ids)
+        }).await?;
+        self.events.batch(NOTE_CREATED, &result);
+        Ok(result)
+    }
+
+    async fn batch_update_notes(&self, notes: &[Note]) -> Result<BatchResult, AppError> {
+        let notes = notes.to_vec();
+        let result = self.run(move |conn| {
+            let ids: Vec<Option<i64>> = notes.iter().map(|n| n.id).collect();
+            let tx = conn.transaction()?;
+            for (index, note) in notes.iter().enumerate() {
+                let updated = note.id
+                    .ok_or_else(|| AppError::validation("Note id is required"))
+                    .and_then(|id| {
+                        write_update(&tx, id, note)?;
+                        set_tags(&tx, id, &note.tags)
+                    });
+                if let Err(e) = updated {
+                    return Ok(BatchResult::rolled_back(ids, index, e));
+                }
+            }
+            tx.commit()?;
+
+            batch_written(conn, &ids.into_iter().flatten().collect::<Vec<_>>())
+        }).await?;
+        self.events.batch(NOTE_UPDATED, &result);
+        Ok(result)
+    }
+
+    async fn batch_delete_notes(&self, ids: &[i64]) -> Result<BatchResult, AppError> {
+        let ids = ids.to_vec();
+        let result = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            for (index, &id) in ids.iter().enumerate() {
+                let trashed = trash_note(&tx, id)
+                    .and_then(|rows| if rows > 0 { Ok(()) } else { Err(AppError::not_found("Note")) });
+                if let Err(e) = trashed {
+                    return Ok(BatchResult::rolled_back(ids.iter().copied().map(Some).collect(), index, e));
+                }
+            }
+            tx.commit()?;
+
+            batch_written(conn, &ids)
+        }).await?;
+        self.events.batch(NOTE_DELETED, &result);
+        Ok(result)
+    }
+
+    async fn list_trash(&self) -> Result<Vec<Note>, AppError> {
+        self.run(|conn| {
+            query_notes(conn, &format!(
+                "SELECT {NOTE_COLUMNS} FROM notes WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC"
+            ), &[])
+        }).await
+    }
+
+    async fn restore_note(&self, id: i64) -> Result<Note, AppError> {
+        let restored = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let changed = tx.execute(
+                "UPDATE notes SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+                params![id],
+            )?;
+            if changed == 0 {
+                return Err(AppError::NotFound("Note not found in trash".into()));
+            }
+            enqueue(&tx, id, OutboxOp::Restore, None)?;
+            tx.commit()?;
+            get_note(conn, id)
+        }).await?;
+        self.events.created(&restored);
+        Ok(restored)
+    }
+
+    async fn purge_note(&self, id: i64) -> Result<DeleteResult, AppError> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let changed = tx.execute(
+                "DELETE FROM notes WHERE id = ?1 AND deleted_at IS NOT NULL",
+                params![id],
+            )?;
+            if changed > 0 {
+                enqueue(&tx, id, OutboxOp::Purge, None)?;
+            }
+            tx.commit()?;
+            Ok(DeleteResult { id, rows_affected: changed as u64 })
+        }).await
+    }
+
+    async fn purge_expired_trash(&self, retention_days: u32) -> Result<u64, AppError> {
+        let cutoff = format_time(&(Utc::now() - Duration::days(retention_days.into())));
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let expired: Vec<i64> = {
+                let mut stmt = tx.prepare("SELECT id FROM notes WHERE deleted_at IS NOT NULL AND deleted_at < ?1")?;
+                let ids = stmt.query_map(params![cutoff], |row| row.get(0))?;
+                ids.collect::<Result<_, _>>()?
+            };
+            for id in &expired {
+                tx.execute("DELETE FROM notes WHERE id = ?1", params![id])?;
+                enqueue(&tx, *id, OutboxOp::Purge, None)?;
+            }
+            tx.commit()?;
+            Ok(expired.len() as u64)
+        }).await
+    }
+
+    async fn add_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let tags = tags.to_vec();
+        let note = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            ensure_note_exists(&tx, note_id)?;
+            attach_tags(&tx, note_id, &tags)?;
+            enqueue(&tx, note_id, OutboxOp::Tags, None)?;
+            tx.commit()?;
+            get_note(conn, note_id)
+        }).await?;
+        self.events.updated(&note);
+        Ok(note.tags)
+    }
+
+    async fn remove_tags(&self, note_id: i64, tags: &[String]) -> Result<Vec<String>, AppError> {
+        let tags = normalize_tags(tags);
+        let note = self.run(move |conn| {
+            let tx = conn.transaction()?;
+            ensure_note_exists(&tx, note_id)?;
+            for name in &tags {
+                tx.execute(
+                    "DELETE FROM note_tags
+                     WHERE note_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+                    params![note_id, name],
+                )?;
+            }
+            delete_unused_tags(&tx)?;
+            enqueue(&tx, note_id, OutboxOp::Tags, None)?;
+            tx.commit()?;
+            get_note(conn, note_id)
+        }).await?;
+        self.events.updated(&note);
+        Ok(note.tags)
+    }
+
+    async fn rename_tag(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let from = from.trim().to_string();
+        let to = normalize_tags(&[to.to_string()]).pop()
+            .ok_or_else(|| AppError::validation("Tag name must not be empty"))?;
+
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            let from_id: i64 = tx.query_row("SELECT id FROM tags WHERE name = ?1", params![from], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| AppError::not_found("Tag"))?;
+            let to_id: Option<i64> = tx.query_row("SELECT id FROM tags WHERE name = ?1", params![to], |row| row.get(0))
+                .optional()?;
+
+            // MySQL gets the rename as a tag update on every affected note.
+            let affected: Vec<i64> = {
+                let mut stmt = tx.prepare("SELECT note_id FROM note_tags WHERE tag_id = ?1")?;
+                let ids = stmt.query_map(params![from_id], |row| row.get(0))?;
+                ids.collect::<Result<_, _>>()?
+            };
+            for note_id in affected {
+                enqueue(&tx, note_id, OutboxOp::Tags, None)?;
+            }
+
+            match to_id {
+                Some(to_id) if to_id != from_id => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+                         SELECT note_id, ?1 FROM note_tags WHERE tag_id = ?2",
+                        params![to_id, from_id],
+                    )?;
+                    tx.execute("DELETE FROM tags WHERE id = ?1", params![from_id])?;
+                },
+                // Same tag (possibly a change of case only) or a brand new name.
+                _ => {
+                    tx.execute("UPDATE tags SET name = ?1 WHERE id = ?2", params![to, from_id])?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    async fn list_tags(&self) -> Result<Vec<TagUsage>, AppError> {
+        self.run(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT t.name, COUNT(n.id)
+                 FROM tags t
+                 LEFT JOIN note_tags nt ON nt.tag_id = t.id
+                 LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+                 GROUP BY t.id, t.name
+                 ORDER BY t.name",
+            )?;
+            let tags = stmt
+                .query_map([], |row| Ok(TagUsage { name: row.get(0)?, note_count: row.get(1)? }))?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(tags)
+        }).await
+    }
+}
+
+fn list_notes(conn: &Connection, query: &NoteListQuery) -> Result<NotePage, AppError> {
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let cursor = match &query.cursor {
+        Some(raw) => Some(
+            NoteCursor::decode(raw)
+                .filter(|c| c.sort_by == query.sort_by && c.direction == query.direction)
+                .ok_or_else(|| AppError::validation("Invalid or mismatched cursor"))?
+        ),
+        None => None,
+    };
+
+    let mut filters: Vec<String> = vec!["deleted_at IS NULL".to_string()];
+    let mut params: Vec<(String, Value)> = Vec::new();
+    let ranges = [
+        (":created_after", "created_at >=", &query.created_after),
+        (":created_before", "created_at <", &query.created_before),
+        (":updated_after", "updated_at >=", &query.updated_after),
+        (":updated_before", "updated_at <", &query.updated_before),
+    ];
+    for (name, condition, value) in ranges {
+        if let Some(value) = value {
+            filters.push(format!("{} {}", condition, name));
+            params.push((name.to_string(), Value::Text(format_time(value))));
+        }
+    }
+
+    let total: u64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM notes WHERE {}", filters.join(" AND ")),
+        named(&params).as_slice(),
+        |row| row.get(0),
+    )?;
+
+    // Same keyset scheme as the MySQL repository; see `NoteRepository::list_notes`.
+    let backward = cursor.as_ref().is_some_and(|c| c.backward);
+    let descending = (query.direction == SortDirection::Desc) != backward;
+    let (cmp, order) = if descending { ("<", "DESC") } else { (">", "ASC") };
+    let column = query.sort_by.column();
+
+    if let Some(cursor) = &cursor {
+        filters.push(format!(
+            "({column} {cmp} :cursor_value OR ({column} = :cursor_value AND id {cmp} :cursor_id))"
+        ));
+        params.push((":cursor_value".to_string(), Value::Text(cursor.value.clone())));
+        params.push((":cursor_id".to_string(), Value::Integer(cursor.id)));
+    }
+    params.push((":limit".to_string(), Value::Integer(i64::from(page_size) + 1)));
+
+    let sql = format!(
+        "SELECT {NOTE_COLUMNS} FROM notes WHERE {} ORDER BY {column} {order}, id {order} LIMIT :limit",
+        filters.join(" AND ")
+    );
+    let mut notes = {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(named(&params).as_slice(), note_from_row)?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let has_more = notes.len() > page_size as usize;
+    notes.truncate(page_size as usize);
+    if backward {
+        notes.reverse();
+    }
+    load_tags(conn, &mut notes)?;
+
+    let (has_next, has_prev) = if backward {
+        (true, has_more)
+    } else {
+        (has_more, cursor.is_some())
+    };
+
+    Ok(NotePage {
+        next_cursor: notes.last()
+            .filter(|_| has_next)
+            .and_then(|n| NoteCursor::at(n, query, false))
+            .map(|c| c.encode()),
+        prev_cursor: notes.first()
+            .filter(|_| has_prev)
+            .and_then(|n| NoteCursor::at(n, query, true))
+            .map(|c| c.encode()),
+        notes,
+        total,
+    })
+}
+
+/// Substring search over title and content. A note matches if it contains
+/// any of the query terms; the score is the number of occurrences.
+fn search_notes(conn: &Connection, query: &SearchQuery) -> Result<SearchResults, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let terms = search::search_terms(query.query.trim(), query.mode);
+
+    if terms.is_empty() {
+        return Ok(SearchResults { hits: Vec::new(), total: 0, page, page_size });
+    }
+
+    let conditions = vec!["(title LIKE ? ESCAPE '\\' OR content LIKE ? ESCAPE '\\')"; terms.len()].join(" OR ");
+    let patterns: Vec<String> = terms.iter().map(|t| format!("%{}%", escape_like(t))).collect();
+    let params: Vec<&dyn ToSql> = patterns.iter()
+        .flat_map(|p| [p as &dyn ToSql, p as &dyn ToSql])
+        .collect();
+
+    let notes = query_notes(conn, &format!(
+        "SELECT {NOTE_COLUMNS} FROM notes WHERE deleted_at IS NULL AND ({conditions})"
+    ), &params)?;
+
+    let mut scored: Vec<(Note, f64)> = notes.into_iter()
+        .map(|note| {
+            let haystack = format!("{} {}", note.title, note.content.as_deref().unwrap_or_default()).to_lowercase();
+            let score = terms.iter().map(|t| haystack.matches(t.as_str()).count()).sum::<usize>();
+            (note, score as f64)
+        })
+        .collect();
+    scored.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(b.id.cmp(&a.id)));
+
+    let total = scored.len() as u64;
+    let hits = scored.into_iter()
+        .skip(((page - 1) * page_size) as usize)
+        .take(page_size as usize)
+        .map(|(note, score)| SearchHit {
+            title_highlight: search::highlight(&note.title, &terms),
+            snippet: search::snippet(note.content.as_deref().unwrap_or_default(), &terms),
+            score,
+            note,
+        })
+        .collect();
+
+    Ok(SearchResults { hits, total, page, page_size })
+}
+
+/// Inserts a note created offline, records it in the outbox and returns
+/// its id.
+fn insert_note(conn: &Connection, note: &Note) -> Result<i64, AppError> {
+    let now = format_time(&Utc::now());
+    // Negative ids never collide with MySQL's; sync swaps in the real id
+    // once the note has been pushed.
+    let id: i64 = conn.query_row("SELECT MIN(COALESCE(MIN(id), 0), 0) - 1 FROM notes", [], |row| row.get(0))?;
+    conn.execute(
+        "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![id, note.title, note.content, now],
+    )?;
+    attach_tags(conn, id, &note.tags)?;
+    enqueue(conn, id, OutboxOp::Create, None)?;
+    Ok(id)
+}
+
+/// Writes title and content and records the update in the outbox. A stale
+/// `note.version` is rejected with `AppError::Conflict`.
+fn write_update(conn: &Connection, id: i64, note: &Note) -> Result<(), AppError> {
+    let version: u32 = conn.query_row(
+        "SELECT version FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("Note"))?;
+
+    if note.version.is_some_and(|expected| expected != version) {
+        return Err(AppError::Conflict {
+            message: format!("Note was modified elsewhere (now at version {})", version),
+            current: Some(Box::new(get_note(conn, id)?)),
+        });
+    }
+
+    let changed = conn.execute(
+        "UPDATE notes SET title = ?1, content = ?2, version = version + 1, updated_at = ?3
+         WHERE id = ?4 AND deleted_at IS NULL",
+        params![note.title, note.content, format_time(&Utc::now()), id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found("Note"));
+    }
+    enqueue(conn, id, OutboxOp::Update, Some(version))?;
+    Ok(())
+}
+
+/// Moves a note to the trash. Returns the rows changed: zero if it was
+/// missing or already there.
+fn trash_note(conn: &Connection, id: i64) -> Result<u64, AppError> {
+    let changed = conn.execute(
+        "UPDATE notes SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        params![format_time(&Utc::now()), id],
+    )?;
+    if changed > 0 {
+        enqueue(conn, id, OutboxOp::Delete, None)?;
+    }
+    Ok(changed as u64)
+}
+
+fn set_tags(conn: &Connection, note_id: i64, tags: &[String]) -> Result<(), AppError> {
+    conn.execute("DELETE FROM note_tags WHERE note_id = ?1", params![note_id])?;
+    attach_tags(conn, note_id, tags)?;
+    delete_unused_tags(conn)?;
+    enqueue(conn, note_id, OutboxOp::Tags, None)
+}
+
+fn batch_written(conn: &Connection, ids: &[i64]) -> Result<BatchResult, AppError> {
+    let items = ids.iter()
+        .map(|&id| Ok((id, get_note_any(conn, id)?)))
+        .collect::<Result<Vec<_>, AppError>>()?;
+    Ok(BatchResult::committed(items))
+}
+
+fn get_note(conn: &Connection, id: i64) -> Result<Note, AppError> {
+    let mut note = conn.query_row(
+        &format!("SELECT {NOTE_COLUMNS} FROM notes WHERE id = ?1 AND deleted_at IS NULL"),
+        params![id],
+        note_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("Note"))?;
+    note.tags = tags_for_note(conn, id)?;
+    Ok(note)
+}
+
+fn get_note_any(conn: &Connection, id: i64) -> Result<Option<Note>, AppError> {
+    let note = conn.query_row(
+        &format!("SELECT {NOTE_COLUMNS} FROM notes WHERE id = ?1"),
+        params![id],
+        note_from_row,
+    )
+    .optional()?;
+
+    match note {
+        Some(mut note) => {
+            note.tags = tags_for_note(conn, id)?;
+            Ok(Some(note))
+        },
+        None => Ok(None),
+    }
+}
+
+fn query_notes(conn: &Connection, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<Note>, AppError> {
+    let mut stmt = conn.prepare(sql)?;
+    let mut notes = stmt.query_map(params, note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    load_tags(conn, &mut notes)?;
+    Ok(notes)
+}
+
+fn note_from_row(row: &Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get(2)?,
+        created_at: parse_time(row.get(3)?),
+        updated_at: parse_time(row.get(4)?),
+        deleted_at: parse_time(row.get(5)?),
+        version: row.get(6)?,
+        tags: Vec::new(),
+    })
+}
+
+fn load_tags(conn: &Connection, notes: &mut [Note]) -> Result<(), AppError> {
+    for note in notes.iter_mut() {
+        if let Some(id) = note.id {
+            note.tags = tags_for_note(conn, id)?;
+        }
+    }
+    Ok(())
+}
+
+fn tags_for_note(conn: &Connection, note_id: i64) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT t.name FROM note_tags nt
+         JOIN tags t ON t.id = nt.tag_id
+         WHERE nt.note_id = ?1
+         ORDER BY t.name",
+    )?;
+    let tags = stmt.query_map(params![note_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
+
+fn attach_tags(conn: &Connection, note_id: i64, tags: &[String]) -> Result<(), AppError> {
+    for name in normalize_tags(tags) {
+        conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![name])?;
+        conn.execute(
+            "INSERT OR IGNORE INTO note_tags (note_id, tag_id)
+             SELECT ?1, id FROM tags WHERE name = ?2",
+            params![note_id, name],
+        )?;
+    }
+    Ok(())
+}
+
+fn ensure_note_exists(conn: &Connection, note_id: i64) -> Result<(), AppError> {
+    conn.query_row(
+        "SELECT id FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+        params![note_id],
+        |_| Ok(()),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found("Note"))
+}
+
+fn delete_unused_tags(conn: &Connection) -> Result<(), AppError> {
+    conn.execute("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM note_tags)", [])?;
+    Ok(())
+}
+
+fn named(params: &[(String, Value)]) -> Vec<(&str, &dyn ToSql)> {
+    params.iter().map(|(name, value)| (name.as_str(), value as &dyn ToSql)).collect()
+}
+
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_time(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
@@ -0,0 +1,318 @@
+// src/store/sqlite/outbox.rs
+
+//! Offline write log and MySQL mirror for `SqliteStore`.
+//!
+//! Every write made through the SQLite store appends an entry naming the
+//! note and what happened to it; the note's row holds the data. `db::sync`
+//! replays the entries against MySQL in order and then refreshes the mirror
+//! from MySQL.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::error::AppError;
+use crate::models::Note;
+use super::{attach_tags, delete_unused_tags, format_time, get_note_any, SqliteStore};
+
+const SCHEMA_VERSION: i64 = 4;
+
+const OUTBOX_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS sync_outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    note_id INTEGER NOT NULL,
+    op TEXT NOT NULL,
+    base_version INTEGER,
+    queued_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_sync_outbox_note ON sync_outbox (note_id);
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutboxOp {
+    /// Note created offline; it has a negative id until pushed.
+    Create,
+    /// Title or content changed. `base_version` is the MySQL version the
+    /// edit started from.
+    Update,
+    /// Tags changed; replayed without a version check.
+    Tags,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl OutboxOp {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutboxOp::Create => "create",
+            OutboxOp::Update => "update",
+            OutboxOp::Tags => "tags",
+            OutboxOp::Delete => "delete",
+            OutboxOp::Restore => "restore",
+            OutboxOp::Purge => "purge",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(OutboxOp::Create),
+            "update" => Some(OutboxOp::Update),
+            "tags" => Some(OutboxOp::Tags),
+            "delete" => Some(OutboxOp::Delete),
+            "restore" => Some(OutboxOp::Restore),
+            "purge" => Some(OutboxOp::Purge),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub note_id: i64,
+    pub op: OutboxOp,
+    pub base_version: Option<u32>,
+    /// MySQL id of the note this entry created, recorded before that insert
+    /// committed. A retry finding it set must not create the note again.
+    pub remote_id: Option<i64>,
+}
+
+/// Brings the outbox up to date. A database created before sync existed
+/// has its notes, all created offline, renumbered to negative ids and
+/// queued for upload. Runs with foreign keys off so `note_tags` can follow.
+pub(super) fn migrate(conn: &mut Connection) -> Result<(), AppError> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version >= SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let tx = conn.transaction()?;
+    if version < 2 {
+        tx.execute_batch(OUTBOX_SCHEMA)?;
+        tx.execute("UPDATE note_tags SET note_id = -note_id WHERE note_id > 0", [])?;
+        tx.execute("UPDATE notes SET id = -id WHERE id > 0", [])?;
+        tx.execute(
+            "INSERT INTO sync_outbox (note_id, op, queued_at)
+             SELECT id, 'create', ?1 FROM notes ORDER BY id DESC",
+            params![format_time(&Utc::now())],
+        )?;
+    }
+    if version < 3 {
+        tx.execute_batch("ALTER TABLE sync_outbox ADD COLUMN remote_id INTEGER;")?;
+    }
+    if version < 4 {
+        tx.execute_batch("ALTER TABLE sync_outbox ADD COLUMN error TEXT;")?;
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Records a write. Entries are coalesced: a pending create already carries
+/// the note's final state, repeated updates keep the first base version, and
+/// purging a note that never reached MySQL drops its entries altogether.
+pub(super) fn enqueue(conn: &Connection, note_id: i64, op: OutboxOp, base_version: Option<u32>) -> Result<(), AppError> {
+    if op == OutboxOp::Purge && note_id < 0 {
+        conn.execute("DELETE FROM sync_outbox WHERE note_id = ?1", params![note_id])?;
+        return Ok(());
+    }
+
+    if op != OutboxOp::Purge {
+        let covered: Option<i64> = conn.query_row(
+            "SELECT id FROM sync_outbox
+             WHERE note_id = ?1 AND (op = 'create' OR (op = ?2 AND op IN ('update', 'tags')))
+             LIMIT 1",
+            params![note_id, op.as_str()],
+            |row| row.get(0),
+        )
+        .optional()?;
+        if covered.is_some() {
+            return Ok(());
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO sync_outbox (note_id, op, base_version, queued_at) VALUES (?1, ?2, ?3, ?4)",
+        params![note_id, op.as_str(), base_version, format_time(&Utc::now())],
+    )?;
+    Ok(())
+}
+
+impl SqliteStore {
+    /// Outbox entries in the order they were recorded.
+    pub async fn pending_ops(&self) -> Result<Vec<OutboxEntry>, AppError> {
+        self.run(|conn| {
+            let mut stmt = conn.prepare("SELECT id, note_id, op, base_version, remote_id FROM sync_outbox ORDER BY id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?, row.get(3)?, row.get(4)?))
+            })?;
+
+            let mut entries = Vec::new();
+            for row in rows {
+                let (id, note_id, op, base_version, remote_id) = row?;
+                match OutboxOp::parse(&op) {
+                    Some(op) => entries.push(OutboxEntry { id, note_id, op, base_version, remote_id }),
+                    None => log::warn!("Skipping outbox entry {} with unknown op {:?}", id, op),
+                }
+            }
+            Ok(entries)
+        }).await
+    }
+
+    pub async fn pending_count(&self) -> Result<u64, AppError> {
+        self.run(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM sync_outbox", [], |row| row.get(0))?)).await
+    }
+
+    /// Entries MySQL rejected during the last sync. They stay queued.
+    pub async fn failed_count(&self) -> Result<u64, AppError> {
+        self.run(|conn| {
+            Ok(conn.query_row("SELECT COUNT(*) FROM sync_outbox WHERE error IS NOT NULL", [], |row| row.get(0))?)
+        }).await
+    }
+
+    /// Keeps an entry MySQL rejected, noting why, for a later sync to retry.
+    pub async fn mark_failed(&self, entry_id: i64, error: String) -> Result<(), AppError> {
+        self.run(move |conn| {
+            conn.execute("UPDATE sync_outbox SET error = ?1 WHERE id = ?2", params![error, entry_id])?;
+            Ok(())
+        }).await
+    }
+
+    /// Removes a replayed entry.
+    pub async fn ack(&self, entry_id: i64) -> Result<(), AppError> {
+        self.run(move |conn| {
+            conn.execute("DELETE FROM sync_outbox WHERE id = ?1", params![entry_id])?;
+            Ok(())
+        }).await
+    }
+
+    /// Records the MySQL id an entry is about to create. Called before the
+    /// insert commits, so a crash after the commit leaves the id behind.
+    pub async fn record_remote_id(&self, entry_id: i64, remote_id: i64) -> Result<(), AppError> {
+        self.run(move |conn| {
+            conn.execute("UPDATE sync_outbox SET remote_id = ?1 WHERE id = ?2", params![remote_id, entry_id])?;
+            Ok(())
+        }).await
+    }
+
+    /// Removes every entry for a note, e.g. once its create has been pushed.
+    pub async fn ack_note(&self, note_id: i64) -> Result<(), AppError> {
+        self.run(move |conn| {
+            conn.execute("DELETE FROM sync_outbox WHERE note_id = ?1", params![note_id])?;
+            Ok(())
+        }).await
+    }
+
+    /// Fetches a note whether or not it is in the trash.
+    pub async fn find_note(&self, id: i64) -> Result<Option<Note>, AppError> {
+        self.run(move |conn| get_note_any(conn, id)).await
+    }
+
+    /// Drops a note from the cache without recording a write. A note with
+    /// pending entries is kept, like in `mirror_note`.
+    pub async fn forget_note(&self, id: i64) -> Result<(), AppError> {
+        self.run(move |conn| {
+            conn.execute(
+                "DELETE FROM notes WHERE id = ?1 AND id NOT IN (SELECT note_id FROM sync_outbox)",
+                params![id],
+            )?;
+            delete_unused_tags(conn)?;
+            Ok(())
+        }).await
+    }
+
+    /// Stores MySQL's copy of a note without recording a write. Notes with
+    /// pending outbox entries are left alone so unsynced edits survive.
+    pub async fn mirror_note(&self, note: &Note) -> Result<(), AppError> {
+        let note = note.clone();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            mirror(&tx, &note)?;
+            delete_unused_tags(&tx)?;
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    /// Replaces the mirror with the full set of MySQL notes, trash included.
+    /// Local-only notes and notes with pending entries are kept. Returns
+    /// whether any cached note changed.
+    pub async fn replace_mirror(&self, notes: Vec<Note>) -> Result<bool, AppError> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute_batch("CREATE TEMP TABLE IF NOT EXISTS mirror_ids (id INTEGER PRIMARY KEY); DELETE FROM mirror_ids;")?;
+            let mut changed = false;
+            for note in &notes {
+                tx.execute("INSERT OR IGNORE INTO mirror_ids (id) VALUES (?1)", params![note.id])?;
+                changed |= mirror(&tx, note)?;
+            }
+            let removed = tx.execute(
+                "DELETE FROM notes
+                 WHERE id > 0
+                   AND id NOT IN (SELECT id FROM mirror_ids)
+                   AND id NOT IN (SELECT note_id FROM sync_outbox)",
+                [],
+            )?;
+            delete_unused_tags(&tx)?;
+            tx.commit()?;
+            Ok(changed || removed > 0)
+        }).await
+    }
+}
+
+/// Returns whether the cached copy changed.
+fn mirror(conn: &Connection, note: &Note) -> Result<bool, AppError> {
+    let Some(id) = note.id else {
+        return Ok(false);
+    };
+    let pending: Option<i64> = conn.query_row(
+        "SELECT id FROM sync_outbox WHERE note_id = ?1 LIMIT 1",
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()?;
+    if pending.is_some() {
+        return Ok(false);
+    }
+    if get_note_any(conn, id)?.is_some_and(|cached| same_note(&cached, note)) {
+        return Ok(false);
+    }
+
+    conn.execute(
+        "INSERT INTO notes (id, title, content, created_at, updated_at, deleted_at, version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (id) DO UPDATE SET
+             title = excluded.title,
+             content = excluded.content,
+             created_at = excluded.created_at,
+             updated_at = excluded.updated_at,
+             deleted_at = excluded.deleted_at,
+             version = excluded.version",
+        params![
+            id,
+            note.title,
+            note.content,
+            note.created_at.as_ref().map(format_time).unwrap_or_else(|| format_time(&Utc::now())),
+            note.updated_at.as_ref().map(format_time).unwrap_or_else(|| format_time(&Utc::now())),
+            note.deleted_at.as_ref().map(format_time),
+            note.version.unwrap_or(1),
+        ],
+    )?;
+    conn.execute("DELETE FROM note_tags WHERE note_id = ?1", params![id])?;
+    attach_tags(conn, id, &note.tags)?;
+    Ok(true)
+}
+
+fn same_note(a: &Note, b: &Note) -> bool {
+    a.title == b.title
+        && a.content == b.content
+        && a.updated_at == b.updated_at
+        && a.deleted_at == b.deleted_at
+        && a.version == b.version.or(Some(1))
+        && sorted(&a.tags) == sorted(&b.tags)
+}
+
+fn sorted(tags: &[String]) -> Vec<&String> {
+    let mut tags: Vec<&String> = tags.iter().collect();
+    tags.sort();
+    tags
+}
@@ -0,0 +1,149 @@
+// src/timestamps.rs
+
+//! Conversions between MySQL `TIMESTAMP` values and `chrono` UTC times.
+//!
+//! Every pooled connection runs with `time_zone = '+00:00'` (see
+//! `db::create_database_pool`), so the values MySQL hands back are already
+//! UTC and can be read without guessing an offset.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, SecondsFormat, Timelike, Utc};
+use mysql_async::Value;
+
+/// Statement run on every new connection so `TIMESTAMP` columns are read
+/// and written in UTC regardless of the server's default zone.
+pub const SESSION_TIME_ZONE: &str = "SET time_zone = '+00:00'";
+
+/// Text-protocol format; the fraction is optional and up to 6 digits.
+const SQL_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Reads a column value as a UTC time. `NULL`, the zero date and anything
+/// unparseable become `None`.
+pub fn from_value(value: Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Date(year, month, day, hour, minute, second, micros) => {
+            NaiveDate::from_ymd_opt(year.into(), month.into(), day.into())?
+                .and_hms_micro_opt(hour.into(), minute.into(), second.into(), micros)
+                .map(|naive| naive.and_utc())
+        },
+        Value::Bytes(bytes) => {
+            let text = String::from_utf8_lossy(&bytes);
+            NaiveDateTime::parse_from_str(&text, SQL_FORMAT)
+                .ok()
+                .map(|naive| naive.and_utc())
+        },
+        _ => None,
+    }
+}
+
+/// Binds a UTC time as a parameter, keeping microseconds.
+pub fn to_value(time: &DateTime<Utc>) -> Value {
+    Value::Date(
+        time.year() as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+        time.nanosecond() / 1_000,
+    )
+}
+
+/// Serde adapter for `Option<DateTime<Utc>>` fields sent to the frontend.
+/// Always RFC 3339 in UTC with microseconds, e.g. `2024-05-01T12:30:00.000000Z`.
+pub mod rfc3339_option {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::Micros, true)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Accepts any RFC 3339 offset and converts to UTC.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let raw: Option<String> = Option::deserialize(deserializer)?;
+        raw.map(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(micros: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 15).unwrap() + chrono::Duration::microseconds(micros.into())
+    }
+
+    #[test]
+    fn binary_protocol_round_trip_keeps_microseconds() {
+        let time = utc(123_456);
+        let value = to_value(&time);
+
+        assert_eq!(value, Value::Date(2024, 5, 1, 12, 30, 15, 123_456));
+        assert_eq!(from_value(value), Some(time));
+    }
+
+    #[test]
+    fn to_value_truncates_below_timestamp_precision() {
+        // TIMESTAMP(6) stores microseconds; anything finer is dropped.
+        let time = utc(0) + chrono::Duration::nanoseconds(1_999);
+
+        assert_eq!(to_value(&time), Value::Date(2024, 5, 1, 12, 30, 15, 1));
+    }
+
+    #[test]
+    fn text_protocol_with_and_without_fraction() {
+        assert_eq!(from_value(Value::Bytes(b"2024-05-01 12:30:15".to_vec())), Some(utc(0)));
+        assert_eq!(from_value(Value::Bytes(b"2024-05-01 12:30:15.000042".to_vec())), Some(utc(42)));
+    }
+
+    #[test]
+    fn zero_date_and_null_are_none() {
+        assert_eq!(from_value(Value::Date(0, 0, 0, 0, 0, 0, 0)), None);
+        assert_eq!(from_value(Value::Bytes(b"0000-00-00 00:00:00".to_vec())), None);
+        assert_eq!(from_value(Value::NULL), None);
+    }
+
+    #[test]
+    fn timestamp_range_edges_round_trip() {
+        let first = Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 1).unwrap();
+        let last = Utc.with_ymd_and_hms(2038, 1, 19, 3, 14, 7).unwrap() + chrono::Duration::microseconds(999_999);
+
+        for time in [first, last] {
+            assert_eq!(from_value(to_value(&time)), Some(time));
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct Wrapper {
+        #[serde(with = "rfc3339_option")]
+        at: Option<DateTime<Utc>>,
+    }
+
+    #[test]
+    fn serializes_as_rfc3339_utc_with_micros() {
+        let json = serde_json::to_string(&Wrapper { at: Some(utc(5)) }).unwrap();
+        assert_eq!(json, r#"{"at":"2024-05-01T12:30:15.000005Z"}"#);
+
+        let json = serde_json::to_string(&Wrapper { at: None }).unwrap();
+        assert_eq!(json, r#"{"at":null}"#);
+    }
+
+    #[test]
+    fn deserializes_offsets_to_utc() {
+        let parsed: Wrapper = serde_json::from_str(r#"{"at":"2024-05-01T14:30:15.000005+02:00"}"#).unwrap();
+        assert_eq!(parsed, Wrapper { at: Some(utc(5)) });
+
+        let json = serde_json::to_string(&parsed).unwrap();
+        let again: Wrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(again, parsed);
+    }
+}
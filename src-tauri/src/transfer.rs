@@ -0,0 +1,280 @@
+// src/transfer.rs
+
+//! Moving notes in and out of the app as files.
+//!
+//! Markdown writes one file per note and is meant for keeping notes in git.
+//! JSON and CSV put every note in a single file for moving between
+//! machines; they stream in both directions and report progress with
+//! `transfer-progress` events.
+
+pub mod csv;
+pub mod json;
+pub mod markdown;
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use tauri::Emitter;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use crate::db::tags;
+use crate::error::AppError;
+use crate::models::{BulkExportSummary, ImportError, ImportMode, ImportSummary, Note};
+use crate::store::NoteStore;
+use crate::validation;
+
+/// Notes fetched per query during a bulk export.
+const EXPORT_BATCH_SIZE: usize = 500;
+/// Notes parsed ahead of the database during a bulk import.
+const IMPORT_BUFFER: usize = 256;
+/// Imported notes between two progress events.
+const IMPORT_PROGRESS_EVERY: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TransferFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TransferDirection {
+    Export,
+    Import,
+}
+
+/// Payload of the `transfer-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub direction: TransferDirection,
+    pub format: TransferFormat,
+    pub processed: u64,
+    /// Known for exports only; imports are read as they stream in.
+    pub total: Option<u64>,
+    pub done: bool,
+}
+
+fn emit_progress(app: &tauri::AppHandle, progress: TransferProgress) {
+    if let Err(e) = app.emit("transfer-progress", progress) {
+        log::warn!("Failed to emit transfer progress: {}", e);
+    }
+}
+
+/// Turns notes into the bytes of one bulk export format.
+trait Encoder {
+    const FORMAT: TransferFormat;
+
+    fn begin(&mut self, out: &mut Vec<u8>) -> Result<(), AppError>;
+    fn encode(&mut self, notes: &[Note], out: &mut Vec<u8>) -> Result<(), AppError>;
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), AppError>;
+}
+
+/// A parsed note, or why it could not be parsed, labelled with where it
+/// came from in the file.
+type Record = (String, Result<Note, String>);
+
+/// Writes every note that is not in the trash to `path`, a batch at a time.
+/// The file is written under a temporary name and renamed at the end, so a
+/// failed export never leaves a truncated file behind.
+async fn export<E: Encoder>(
+    app: &tauri::AppHandle,
+    store: &dyn NoteStore,
+    path: &Path,
+    encoder: E,
+) -> Result<BulkExportSummary, AppError> {
+    let mut partial = OsString::from(path.as_os_str());
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let result = write_export(app, store, &partial, encoder).await;
+    let exported = match result {
+        Ok(exported) => exported,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&partial, path).await.map_err(|e| AppError::io(path, e))?;
+
+    emit_progress(app, TransferProgress {
+        direction: TransferDirection::Export,
+        format: E::FORMAT,
+        processed: exported,
+        total: Some(exported),
+        done: true,
+    });
+    Ok(BulkExportSummary {
+        path: path.display().to_string(),
+        exported,
+    })
+}
+
+async fn write_export<E: Encoder>(
+    app: &tauri::AppHandle,
+    store: &dyn NoteStore,
+    path: &Path,
+    mut encoder: E,
+) -> Result<u64, AppError> {
+    let total = store.count_notes().await?;
+    let file = tokio::fs::File::create(path).await.map_err(|e| AppError::io(path, e))?;
+    let mut out = tokio::io::BufWriter::new(file);
+    let mut buf = Vec::new();
+
+    encoder.begin(&mut buf)?;
+    let mut exported = 0;
+    // Notes written offline have negative ids until they are synced.
+    let mut after = i64::MIN;
+    loop {
+        let batch = store.notes_after(after, EXPORT_BATCH_SIZE).await?;
+        let Some(last_id) = batch.last().and_then(|n| n.id) else {
+            break;
+        };
+        after = last_id;
+
+        encoder.encode(&batch, &mut buf)?;
+        out.write_all(&buf).await.map_err(|e| AppError::io(path, e))?;
+        buf.clear();
+
+        exported += batch.len() as u64;
+        emit_progress(app, TransferProgress {
+            direction: TransferDirection::Export,
+            format: E::FORMAT,
+            processed: exported,
+            // Notes created during the export can push past the count.
+            total: Some(total.max(exported)),
+            done: false,
+        });
+    }
+    encoder.finish(&mut buf)?;
+    out.write_all(&buf).await.map_err(|e| AppError::io(path, e))?;
+    out.flush().await.map_err(|e| AppError::io(path, e))?;
+
+    Ok(exported)
+}
+
+/// Imports the notes `read` parses out of `path`. Parsing runs on a
+/// blocking thread and hands notes over as they are read, so the file is
+/// never held in memory whole. Bad records are reported and skipped; a
+/// syntax error ends the import after the records before it.
+async fn import<R>(
+    app: &tauri::AppHandle,
+    store: &dyn NoteStore,
+    path: &Path,
+    format: TransferFormat,
+    mode: ImportMode,
+    read: R,
+) -> Result<ImportSummary, AppError>
+where
+    R: FnOnce(std::fs::File, mpsc::Sender<Record>) -> Result<(), AppError> + Send + 'static,
+{
+    let file = std::fs::File::open(path).map_err(|e| AppError::io(path, e))?;
+    let (tx, mut rx) = mpsc::channel(IMPORT_BUFFER);
+    let reader = tokio::task::spawn_blocking(move || read(file, tx));
+
+    let mut summary = ImportSummary::default();
+    let mut processed = 0;
+    while let Some((source, record)) = rx.recv().await {
+        let result = match record {
+            Ok(note) => write_note(store, note, mode).await,
+            Err(message) => Err(AppError::validation(message)),
+        };
+        // Every later note would fail the same way.
+        if let Err(e @ AppError::DatabaseUnavailable(_)) = result {
+            return Err(e);
+        }
+        tally(&mut summary, source, result);
+
+        processed += 1;
+        if processed % IMPORT_PROGRESS_EVERY == 0 {
+            emit_progress(app, TransferProgress {
+                direction: TransferDirection::Import,
+                format,
+                processed,
+                total: None,
+                done: false,
+            });
+        }
+    }
+
+    match reader.await {
+        Ok(Ok(())) => {},
+        Ok(Err(e)) => summary.errors.push(ImportError {
+            source: file_name(path),
+            message: e.to_string(),
+        }),
+        Err(e) => return Err(AppError::Internal(format!("Import reader failed: {}", e))),
+    }
+
+    emit_progress(app, TransferProgress {
+        direction: TransferDirection::Import,
+        format,
+        processed,
+        total: Some(processed),
+        done: true,
+    });
+    Ok(summary)
+}
+
+enum Outcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+fn tally(summary: &mut ImportSummary, source: String, result: Result<Outcome, AppError>) {
+    match result {
+        Ok(Outcome::Created) => summary.created += 1,
+        Ok(Outcome::Updated) => summary.updated += 1,
+        Ok(Outcome::Unchanged) => summary.unchanged += 1,
+        Err(e) => summary.errors.push(ImportError {
+            source,
+            message: e.to_string(),
+        }),
+    }
+}
+
+/// Validates an imported note and writes it. In `Upsert` mode a note whose
+/// id exists updates it, tags included; anything else creates a new note.
+async fn write_note(store: &dyn NoteStore, note: Note, mode: ImportMode) -> Result<Outcome, AppError> {
+    let note = validation::validate_note(&note)?;
+
+    let existing = match (mode, note.id) {
+        (ImportMode::Upsert, Some(id)) => match store.get_note_by_id(id).await {
+            Ok(existing) => Some(existing),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        },
+        _ => None,
+    };
+
+    let Some(existing) = existing else {
+        store.create_note(&Note { id: None, ..note }).await?;
+        return Ok(Outcome::Created);
+    };
+
+    let id = existing.id.unwrap_or_default();
+    let add = tags::missing_tags(&note.tags, &existing.tags);
+    let remove = tags::missing_tags(&existing.tags, &note.tags);
+    let edited = note.title != existing.title
+        || note.content.as_deref().unwrap_or_default() != existing.content.as_deref().unwrap_or_default();
+
+    if !edited && add.is_empty() && remove.is_empty() {
+        return Ok(Outcome::Unchanged);
+    }
+    if edited {
+        // The imported file is the source of truth, so skip the version check.
+        store.update_note(id, &Note { version: None, ..note }).await?;
+    }
+    if !add.is_empty() {
+        store.add_tags(id, &add).await?;
+    }
+    if !remove.is_empty() {
+        store.remove_tags(id, &remove).await?;
+    }
+    Ok(Outcome::Updated)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,208 @@
+// src/transfer/csv.rs
+
+//! All notes as CSV with a header row, for spreadsheets. Tags share one
+//! column, separated by semicolons; a semicolon or backslash inside a tag
+//! is escaped with a backslash.
+
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use crate::error::AppError;
+use crate::models::{BulkExportSummary, ImportMode, ImportSummary, Note};
+use crate::store::NoteStore;
+use crate::timestamps::rfc3339_option;
+use super::{Encoder, Record, TransferFormat};
+
+const HEADERS: [&str; 6] = ["id", "title", "content", "created_at", "updated_at", "tags"];
+const TAG_SEPARATOR: char = ';';
+const TAG_ESCAPE: char = '\\';
+
+pub async fn export_notes(app: &tauri::AppHandle, store: &dyn NoteStore, path: &Path) -> Result<BulkExportSummary, AppError> {
+    super::export(app, store, path, CsvEncoder).await
+}
+
+pub async fn import_notes(
+    app: &tauri::AppHandle,
+    store: &dyn NoteStore,
+    path: &Path,
+    mode: ImportMode,
+) -> Result<ImportSummary, AppError> {
+    super::import(app, store, path, TransferFormat::Csv, mode, read_notes).await
+}
+
+/// One CSV line. Every column but `title` may be left out of an imported
+/// file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Row {
+    #[serde(default)]
+    id: Option<i64>,
+    title: String,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default, with = "rfc3339_option")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    tags: String,
+}
+
+impl From<&Note> for Row {
+    fn from(note: &Note) -> Self {
+        Self {
+            id: note.id,
+            title: note.title.clone(),
+            content: note.content.clone(),
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+            tags: join_tags(&note.tags),
+        }
+    }
+}
+
+impl From<Row> for Note {
+    fn from(row: Row) -> Self {
+        Note {
+            id: row.id,
+            title: row.title,
+            content: row.content,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            deleted_at: None,
+            version: None,
+            tags: split_tags(&row.tags),
+        }
+    }
+}
+
+fn join_tags(tags: &[String]) -> String {
+    let mut cell = String::new();
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            cell.push(TAG_SEPARATOR);
+        }
+        for c in tag.chars() {
+            if c == TAG_SEPARATOR || c == TAG_ESCAPE {
+                cell.push(TAG_ESCAPE);
+            }
+            cell.push(c);
+        }
+    }
+    cell
+}
+
+/// Inverse of [`join_tags`]. A backslash before any other character is
+/// kept as it is, so hand-written cells need no escaping.
+fn split_tags(cell: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut tag = String::new();
+    let mut chars = cell.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            TAG_ESCAPE if matches!(chars.peek(), Some(&TAG_SEPARATOR | &TAG_ESCAPE)) => {
+                tag.extend(chars.next());
+            }
+            TAG_SEPARATOR => tags.push(std::mem::take(&mut tag)),
+            c => tag.push(c),
+        }
+    }
+    tags.push(tag);
+    tags.into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+struct CsvEncoder;
+
+impl Encoder for CsvEncoder {
+    const FORMAT: TransferFormat = TransferFormat::Csv;
+
+    fn begin(&mut self, out: &mut Vec<u8>) -> Result<(), AppError> {
+        let mut writer = WriterBuilder::new().from_writer(out);
+        writer.write_record(HEADERS).map_err(|e| AppError::Internal(e.to_string()))?;
+        writer.flush().map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    fn encode(&mut self, notes: &[Note], out: &mut Vec<u8>) -> Result<(), AppError> {
+        let mut writer = WriterBuilder::new().has_headers(false).from_writer(out);
+        for note in notes {
+            writer.serialize(Row::from(note)).map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        writer.flush().map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+fn read_notes(file: std::fs::File, tx: mpsc::Sender<Record>) -> Result<(), AppError> {
+    let mut reader = ReaderBuilder::new().from_reader(file);
+
+    for (index, row) in reader.deserialize::<Row>().enumerate() {
+        let note = match row {
+            Ok(row) => Ok(Note::from(row)),
+            Err(e) if is_row_error(&e) => Err(e.to_string()),
+            // Anything else, such as invalid UTF-8, ends the file.
+            Err(e) => return Err(AppError::validation(format!("Invalid CSV: {}", e))),
+        };
+        if tx.blocking_send((format!("row {}", index + 1), note)).is_err() {
+            // The import was abandoned.
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Errors confined to one row; reading can carry on with the next.
+fn is_row_error(e: &csv::Error) -> bool {
+    matches!(e.kind(), csv::ErrorKind::Deserialize { .. } | csv::ErrorKind::UnequalLengths { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(note: &Note) -> Note {
+        let mut out = Vec::new();
+        let mut encoder = CsvEncoder;
+        encoder.begin(&mut out).unwrap();
+        encoder.encode(std::slice::from_ref(note), &mut out).unwrap();
+        let mut reader = ReaderBuilder::new().from_reader(out.as_slice());
+        let row: Row = reader.deserialize().next().unwrap().unwrap();
+        Note::from(row)
+    }
+
+    fn note(tags: &[&str]) -> Note {
+        Note {
+            id: Some(7),
+            title: "Groceries, \"weekly\"".into(),
+            content: Some("milk\neggs; bread".into()),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            version: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn tags_with_separators_survive_a_round_trip() {
+        let original = note(&["a;b", "c\\;d", "trailing\\", "plain"]);
+        let read = round_trip(&original);
+        assert_eq!(read.tags, original.tags);
+        assert_eq!(read.title, original.title);
+        assert_eq!(read.content, original.content);
+        assert_eq!(read.id, Some(7));
+    }
+
+    #[test]
+    fn hand_written_cells_split_on_semicolons() {
+        assert_eq!(split_tags("work; home ;;"), ["work", "home"]);
+        assert_eq!(split_tags(r"C:\notes; x"), [r"C:\notes", "x"]);
+        assert!(split_tags("").is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+// src/transfer/json.rs
+
+//! All notes as one JSON array, one note per line, in the shape the note
+//! commands return.
+
+use std::fmt;
+use std::io::BufReader;
+use std::path::Path;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use tokio::sync::mpsc;
+use crate::error::AppError;
+use crate::models::{BulkExportSummary, ImportMode, ImportSummary, Note};
+use crate::store::NoteStore;
+use super::{Encoder, Record, TransferFormat};
+
+pub async fn export_notes(app: &tauri::AppHandle, store: &dyn NoteStore, path: &Path) -> Result<BulkExportSummary, AppError> {
+    super::export(app, store, path, JsonEncoder { first: true }).await
+}
+
+pub async fn import_notes(
+    app: &tauri::AppHandle,
+    store: &dyn NoteStore,
+    path: &Path,
+    mode: ImportMode,
+) -> Result<ImportSummary, AppError> {
+    super::import(app, store, path, TransferFormat::Json, mode, read_notes).await
+}
+
+struct JsonEncoder {
+    first: bool,
+}
+
+impl Encoder for JsonEncoder {
+    const FORMAT: TransferFormat = TransferFormat::Json;
+
+    fn begin(&mut self, out: &mut Vec<u8>) -> Result<(), AppError> {
+        out.push(b'[');
+        Ok(())
+    }
+
+    fn encode(&mut self, notes: &[Note], out: &mut Vec<u8>) -> Result<(), AppError> {
+        for note in notes {
+            out.extend_from_slice(if self.first { b"\n" } else { b",\n" });
+            self.first = false;
+            serde_json::to_writer(&mut *out, note).map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), AppError> {
+        out.extend_from_slice(b"\n]\n");
+        Ok(())
+    }
+}
+
+fn read_notes(file: std::fs::File, tx: mpsc::Sender<Record>) -> Result<(), AppError> {
+    let mut de = serde_json::Deserializer::from_reader(BufReader::new(file));
+    de.deserialize_seq(NoteVisitor { tx })
+        .and_then(|_| de.end())
+        .map_err(|e| AppError::validation(format!("Invalid JSON: {}", e)))
+}
+
+/// Walks the top-level array without collecting it.
+struct NoteVisitor {
+    tx: mpsc::Sender<Record>,
+}
+
+impl<'de> Visitor<'de> for NoteVisitor {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of notes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        // Elements are read as plain JSON first so a note with a bad field
+        // is reported on its own instead of ending the import.
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            index += 1;
+            let note = serde_json::from_value::<Note>(value).map_err(|e| e.to_string());
+            if self.tx.blocking_send((format!("note {}", index), note)).is_err() {
+                // The import was abandoned.
+                break;
+            }
+        }
+        Ok(())
+    }
+}
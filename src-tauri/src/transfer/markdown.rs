@@ -0,0 +1,495 @@
+// src/transfer/markdown.rs
+
+//! One Markdown file per note, with the metadata in YAML front matter:
+//!
+//! ```text
+//! ---
+//! id: 12
+//! title: Shopping list
+//! created_at: 2024-05-01T12:30:15.000000Z
+//! updated_at: 2024-05-02T08:00:00.000000Z
+//! tags:
+//! - home
+//! ---
+//!
+//! Content, byte for byte.
+//! ```
+//!
+//! File names come from the title, so a re-export into the same directory
+//! overwrites each note's file in place and the directory diffs cleanly in
+//! git. Every export lists the files it wrote in a manifest; files the
+//! previous export wrote that this one did not, left behind by notes since
+//! renamed or moved to the trash, are removed so importing the directory
+//! cannot bring them back. Nothing else in the directory is ever deleted.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use crate::models::{ExportSummary, ImportMode, ImportSummary, Note};
+use crate::store::NoteStore;
+use crate::timestamps::rfc3339_option;
+use super::{file_name, tally, write_note};
+
+const EXTENSION: &str = "md";
+/// Not a `.md` file, so imports skip it.
+const MANIFEST: &str = ".notes-export.json";
+const DELIMITER: &str = "---";
+/// Leaves room for a `-NN` suffix within common file name limits.
+const MAX_STEM_CHARS: usize = 80;
+/// Names Windows refuses regardless of extension.
+const RESERVED_STEMS: &[&str] = &[
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default)]
+    id: Option<i64>,
+    title: String,
+    // Written for reference; the database keeps its own timestamps on import.
+    #[serde(default, with = "rfc3339_option")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "rfc3339_option")]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// The files an export wrote, kept in `MANIFEST`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: Vec<String>,
+}
+
+/// Writes every note that is not in the trash to `dir`, creating it if
+/// needed.
+pub async fn export_notes(store: &dyn NoteStore, dir: &Path) -> Result<ExportSummary, AppError> {
+    tokio::fs::create_dir_all(dir).await.map_err(|e| AppError::io(dir, e))?;
+
+    let notes = store.get_all_notes().await?;
+    let mut used: HashSet<String> = HashSet::new();
+    let mut files = Vec::with_capacity(notes.len());
+
+    for note in &notes {
+        let path = free_path(dir, note, &mut used).await;
+        tokio::fs::write(&path, to_markdown(note)?).await.map_err(|e| AppError::io(&path, e))?;
+        files.push(file_name(&path));
+    }
+    let removed = remove_stale(dir, &used).await?;
+    write_manifest(dir, &files).await?;
+
+    Ok(ExportSummary {
+        directory: dir.display().to_string(),
+        files,
+        removed,
+    })
+}
+
+/// Reads every `.md` file directly inside `dir`. With `ImportMode::Upsert`
+/// a file whose `id` matches an existing note updates it; any other file
+/// creates a new note. Files that cannot be parsed or written are reported
+/// and skipped.
+pub async fn import_notes(store: &dyn NoteStore, dir: &Path, mode: ImportMode) -> Result<ImportSummary, AppError> {
+    let paths = markdown_files(dir).await?;
+
+    let mut summary = ImportSummary::default();
+    for path in paths {
+        let result = match read_note(&path).await {
+            Ok(note) => write_note(store, note, mode).await,
+            Err(e) => Err(e),
+        };
+        tally(&mut summary, file_name(&path), result);
+    }
+
+    Ok(summary)
+}
+
+/// The `.md` files directly inside `dir`, sorted by name.
+async fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut paths = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await.map_err(|e| AppError::io(dir, e))?;
+    while let Some(entry) = entries.next_entry().await.map_err(|e| AppError::io(dir, e))? {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(EXTENSION)) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Deletes the files the previous export wrote that this one did not.
+/// Every note still around was just written, so those are left from notes
+/// renamed or no longer exported. A listed file that no longer parses as an
+/// exported note was changed by hand and is kept.
+async fn remove_stale(dir: &Path, written: &HashSet<String>) -> Result<Vec<String>, AppError> {
+    let mut removed = Vec::new();
+    for name in read_manifest(dir).await?.files {
+        // Only plain names in `dir`, whatever the manifest says.
+        if written.contains(&name.to_lowercase()) || file_name(Path::new(&name)) != name {
+            continue;
+        }
+        let path = dir.join(&name);
+        let exported = matches!(read_note(&path).await, Ok(Note { id: Some(_), .. }));
+        if exported {
+            tokio::fs::remove_file(&path).await.map_err(|e| AppError::io(&path, e))?;
+            removed.push(name);
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// The previous export's manifest; empty if there was none, or if it is
+/// unreadable, in which case nothing is removed.
+async fn read_manifest(dir: &Path) -> Result<Manifest, AppError> {
+    let path = dir.join(MANIFEST);
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid export manifest {}: {}", path.display(), e);
+            Manifest::default()
+        })),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(e) => Err(AppError::io(&path, e)),
+    }
+}
+
+async fn write_manifest(dir: &Path, files: &[String]) -> Result<(), AppError> {
+    let path = dir.join(MANIFEST);
+    let manifest = Manifest { files: files.to_vec() };
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| AppError::Internal(e.to_string()))?;
+    tokio::fs::write(&path, json).await.map_err(|e| AppError::io(&path, e))
+}
+
+async fn read_note(path: &Path) -> Result<Note, AppError> {
+    let text = tokio::fs::read_to_string(path).await.map_err(|e| AppError::io(path, e))?;
+    from_markdown(&text)
+}
+
+fn to_markdown(note: &Note) -> Result<String, AppError> {
+    let front = FrontMatter {
+        id: note.id,
+        title: note.title.clone(),
+        created_at: note.created_at,
+        updated_at: note.updated_at,
+        tags: note.tags.clone(),
+    };
+    let yaml = serde_yaml::to_string(&front).map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(format!(
+        "{delim}\n{yaml}{delim}\n\n{content}",
+        delim = DELIMITER,
+        yaml = yaml,
+        content = note.content.as_deref().unwrap_or_default(),
+    ))
+}
+
+/// Parses a file written by `to_markdown`, or by hand in the same shape.
+/// An empty body becomes `None` content.
+fn from_markdown(text: &str) -> Result<Note, AppError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let malformed = || AppError::validation("File does not start with YAML front matter (---)");
+
+    let rest = strip_line(text, DELIMITER).ok_or_else(malformed)?;
+    let (yaml, body) = split_front_matter(rest).ok_or_else(malformed)?;
+    let front: FrontMatter = serde_yaml::from_str(yaml)
+        .map_err(|e| AppError::validation(format!("Invalid front matter: {}", e)))?;
+
+    // One blank line separates the front matter from the content.
+    let body = body.strip_prefix("\r\n").or_else(|| body.strip_prefix('\n')).unwrap_or(body);
+
+    Ok(Note {
+        id: front.id,
+        title: front.title,
+        content: (!body.is_empty()).then(|| body.to_string()),
+        created_at: front.created_at,
+        updated_at: front.updated_at,
+        deleted_at: None,
+        version: None,
+        tags: front.tags,
+    })
+}
+
+/// Splits at the closing delimiter line into the YAML and what follows it.
+fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            return Some((&text[..offset], &text[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn strip_line<'a>(text: &'a str, line: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(line)?;
+    rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))
+}
+
+/// Picks a file name for the note that no other note in this export uses.
+/// An existing file is only reused if it was exported from the same note,
+/// so unrelated files in the directory are never overwritten.
+async fn free_path(dir: &Path, note: &Note, used: &mut HashSet<String>) -> PathBuf {
+    let stem = file_stem(&note.title);
+
+    for n in 1.. {
+        let name = match n {
+            1 => format!("{}.{}", stem, EXTENSION),
+            n => format!("{}-{}.{}", stem, n, EXTENSION),
+        };
+        // Case-insensitive file systems treat "Todo.md" and "todo.md" alike.
+        if used.contains(&name.to_lowercase()) {
+            continue;
+        }
+
+        let path = dir.join(&name);
+        if belongs_to_other(&path, note.id).await {
+            continue;
+        }
+
+        used.insert(name.to_lowercase());
+        return path;
+    }
+    unreachable!("file name suffixes are unbounded")
+}
+
+async fn belongs_to_other(path: &Path, id: Option<i64>) -> bool {
+    match tokio::fs::read_to_string(path).await {
+        Ok(text) => match from_markdown(&text) {
+            Ok(existing) => id.is_none() || existing.id != id,
+            Err(_) => true,
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(_) => true,
+    }
+}
+
+/// Lower-case title with runs of anything but letters and digits turned
+/// into single dashes, e.g. "Q3 plan: draft #2" becomes `q3-plan-draft-2`.
+fn file_stem(title: &str) -> String {
+    let mut stem = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+        if stem.chars().count() >= MAX_STEM_CHARS {
+            break;
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+
+    if stem.is_empty() {
+        "note".to_string()
+    } else if RESERVED_STEMS.contains(&stem) {
+        format!("{}-note", stem)
+    } else {
+        stem.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::store::SqliteStore;
+
+    /// A store and an export directory under one temp dir, removed on drop.
+    struct TempExport {
+        root: TempDir,
+        store: SqliteStore,
+    }
+
+    impl TempExport {
+        fn new() -> Self {
+            let root = TempDir::new().unwrap();
+            let store = SqliteStore::open(&root.path().join("notes.sqlite3")).unwrap();
+            Self { root, store }
+        }
+
+        fn dir(&self) -> PathBuf {
+            self.root.path().join("export")
+        }
+
+        fn files(&self) -> Vec<String> {
+            let mut names: Vec<String> = std::fs::read_dir(self.dir()).unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        }
+
+        async fn titles(&self) -> Vec<String> {
+            let mut titles: Vec<String> = self.store.get_all_notes().await.unwrap()
+                .into_iter()
+                .map(|note| note.title)
+                .collect();
+            titles.sort();
+            titles
+        }
+    }
+
+    #[tokio::test]
+    async fn reexport_after_rename_replaces_the_old_file() {
+        let t = TempExport::new();
+        let note = t.store.create_note(&Note::new("Old Title".into(), Some("Body".into()))).await.unwrap();
+        export_notes(&t.store, &t.dir()).await.unwrap();
+
+        t.store.update_note(note.id.unwrap(), &Note { title: "New Title".into(), ..note }).await.unwrap();
+        let summary = export_notes(&t.store, &t.dir()).await.unwrap();
+
+        assert_eq!(summary.files, vec!["new-title.md"]);
+        assert_eq!(summary.removed, vec!["old-title.md"]);
+        assert_eq!(t.files(), vec![MANIFEST, "new-title.md"]);
+
+        let imported = import_notes(&t.store, &t.dir(), ImportMode::Upsert).await.unwrap();
+        assert_eq!((imported.created, imported.updated, imported.unchanged), (0, 0, 1));
+        assert_eq!(t.titles().await, vec!["New Title"]);
+    }
+
+    #[tokio::test]
+    async fn reexport_removes_files_of_trashed_notes() {
+        let t = TempExport::new();
+        t.store.create_note(&Note::new("Keep".into(), None)).await.unwrap();
+        let trashed = t.store.create_note(&Note::new("Trash me".into(), None)).await.unwrap();
+        export_notes(&t.store, &t.dir()).await.unwrap();
+
+        t.store.delete_note(trashed.id.unwrap()).await.unwrap();
+        export_notes(&t.store, &t.dir()).await.unwrap();
+        import_notes(&t.store, &t.dir(), ImportMode::Upsert).await.unwrap();
+
+        assert_eq!(t.files(), vec![MANIFEST, "keep.md"]);
+        assert_eq!(t.titles().await, vec!["Keep"]);
+    }
+
+    #[tokio::test]
+    async fn reexport_keeps_files_it_did_not_write() {
+        let t = TempExport::new();
+        t.store.create_note(&Note::new("Exported".into(), None)).await.unwrap();
+        std::fs::create_dir_all(t.dir()).unwrap();
+        std::fs::write(t.dir().join("README.md"), "# Notes\n").unwrap();
+        std::fs::write(t.dir().join("draft.md"), "---\ntitle: Draft\n---\n\nNo id yet\n").unwrap();
+        // Copied in from another export, so it has an id, but this directory
+        // never listed it.
+        std::fs::write(t.dir().join("copied.md"), "---\nid: 99\ntitle: Copied\n---\n\nKeep me\n").unwrap();
+
+        let first = export_notes(&t.store, &t.dir()).await.unwrap();
+        let second = export_notes(&t.store, &t.dir()).await.unwrap();
+
+        assert!(first.removed.is_empty());
+        assert!(second.removed.is_empty());
+        assert_eq!(t.files(), vec![MANIFEST, "README.md", "copied.md", "draft.md", "exported.md"]);
+    }
+
+    #[tokio::test]
+    async fn reexport_keeps_listed_files_edited_by_hand() {
+        let t = TempExport::new();
+        let note = t.store.create_note(&Note::new("Scratch".into(), None)).await.unwrap();
+        export_notes(&t.store, &t.dir()).await.unwrap();
+        t.store.delete_note(note.id.unwrap()).await.unwrap();
+        std::fs::write(t.dir().join("scratch.md"), "Rewritten without front matter\n").unwrap();
+
+        let summary = export_notes(&t.store, &t.dir()).await.unwrap();
+
+        assert!(summary.removed.is_empty());
+        assert_eq!(t.files(), vec![MANIFEST, "scratch.md"]);
+    }
+
+    #[test]
+    fn front_matter_round_trips() {
+        let note = Note {
+            id: Some(12),
+            title: "Q3: \"plan\" --- draft".into(),
+            content: Some("---\nnot front matter\n\n  indented\n".into()),
+            created_at: Some("2024-05-01T12:30:15.123456Z".parse().unwrap()),
+            updated_at: Some("2024-05-02T08:00:00Z".parse().unwrap()),
+            deleted_at: None,
+            version: Some(3),
+            tags: vec!["home".into(), "a: b".into()],
+        };
+
+        let read = from_markdown(&to_markdown(&note).unwrap()).unwrap();
+
+        assert_eq!(read.id, note.id);
+        assert_eq!(read.title, note.title);
+        assert_eq!(read.content, note.content);
+        assert_eq!(read.created_at, note.created_at);
+        assert_eq!(read.updated_at, note.updated_at);
+        assert_eq!(read.tags, note.tags);
+        assert_eq!(read.version, None);
+    }
+
+    #[test]
+    fn parses_hand_written_files() {
+        let read = from_markdown("\u{feff}---\r\ntitle: Hand made\r\n---\r\n").unwrap();
+        assert_eq!((read.id, read.title.as_str(), read.content), (None, "Hand made", None));
+        assert!(read.tags.is_empty());
+
+        assert!(matches!(from_markdown("# No front matter\n"), Err(AppError::Validation { .. })));
+        assert!(matches!(from_markdown("---\ntitle: Unclosed\n"), Err(AppError::Validation { .. })));
+        assert!(matches!(from_markdown("---\nid: twelve\n---\n"), Err(AppError::Validation { .. })));
+    }
+
+    #[test]
+    fn file_stems_are_safe_names() {
+        assert_eq!(file_stem("Q3 plan: draft #2"), "q3-plan-draft-2");
+        assert_eq!(file_stem("../../etc/passwd"), "etc-passwd");
+        assert_eq!(file_stem("CON"), "con-note");
+        assert_eq!(file_stem("!!!"), "note");
+        assert_eq!(file_stem(&"x".repeat(200)).len(), MAX_STEM_CHARS);
+    }
+
+    #[tokio::test]
+    async fn colliding_titles_get_numbered_files() {
+        let t = TempExport::new();
+        for title in ["Todo", "TODO", "todo?"] {
+            t.store.create_note(&Note::new(title.into(), None)).await.unwrap();
+        }
+        std::fs::create_dir_all(t.dir()).unwrap();
+        std::fs::write(t.dir().join("todo.md"), "Someone else's file\n").unwrap();
+
+        let first = export_notes(&t.store, &t.dir()).await.unwrap();
+        let second = export_notes(&t.store, &t.dir()).await.unwrap();
+
+        let mut names = first.files.clone();
+        names.sort();
+        assert_eq!(names, ["todo-2.md", "todo-3.md", "todo-4.md"]);
+        assert_eq!(second.files, first.files);
+        assert_eq!(std::fs::read_to_string(t.dir().join("todo.md")).unwrap(), "Someone else's file\n");
+    }
+
+    #[tokio::test]
+    async fn bad_files_are_reported_and_the_rest_imported() {
+        let t = TempExport::new();
+        std::fs::create_dir_all(t.dir()).unwrap();
+        std::fs::write(t.dir().join("a-good.md"), "---\ntitle: Good\n---\n\nBody\n").unwrap();
+        std::fs::write(t.dir().join("b-plain.md"), "Just text\n").unwrap();
+        std::fs::write(t.dir().join("c-empty-title.md"), "---\ntitle: \"  \"\n---\n").unwrap();
+        std::fs::write(t.dir().join("notes.txt"), "Not Markdown\n").unwrap();
+
+        let summary = import_notes(&t.store, &t.dir(), ImportMode::Upsert).await.unwrap();
+
+        assert_eq!(summary.created, 1);
+        let failed: Vec<&str> = summary.errors.iter().map(|e| e.source.as_str()).collect();
+        assert_eq!(failed, ["b-plain.md", "c-empty-title.md"]);
+        assert_eq!(t.titles().await, vec!["Good"]);
+    }
+
+    #[tokio::test]
+    async fn create_mode_imports_exported_notes_as_copies() {
+        let t = TempExport::new();
+        t.store.create_note(&Note::new("Original".into(), Some("Body".into()))).await.unwrap();
+        export_notes(&t.store, &t.dir()).await.unwrap();
+
+        let summary = import_notes(&t.store, &t.dir(), ImportMode::Create).await.unwrap();
+
+        assert_eq!((summary.created, summary.updated, summary.unchanged), (1, 0, 0));
+        assert_eq!(t.titles().await, vec!["Original", "Original"]);
+    }
+}
@@ -0,0 +1,199 @@
+// src/validation.rs
+
+use serde::Serialize;
+use crate::error::AppError;
+use crate::models::Note;
+
+/// `notes.title` is `VARCHAR(255)`, which MySQL counts in characters.
+pub const TITLE_MAX_CHARS: usize = 255;
+/// `notes.content` is `TEXT`, which is capped at 65,535 bytes.
+pub const CONTENT_MAX_BYTES: usize = 65_535;
+/// `tags.name` is `VARCHAR(64)`.
+pub const TAG_MAX_CHARS: usize = 64;
+
+/// A problem with one field of a payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+/// Checks a note coming from the frontend and returns a cleaned-up copy
+/// ready to be written. Every problem is reported at once so the form can
+/// mark all offending fields.
+pub fn validate_note(note: &Note) -> Result<Note, AppError> {
+    let mut errors = Vec::new();
+
+    let title = strip_control_chars(&note.title, false).trim().to_string();
+    if title.is_empty() {
+        errors.push(FieldError::new("title", "Title must not be empty"));
+    } else if title.chars().count() > TITLE_MAX_CHARS {
+        errors.push(FieldError::new(
+            "title",
+            format!("Title must be at most {} characters", TITLE_MAX_CHARS),
+        ));
+    }
+
+    let content = note.content.as_deref().map(|c| strip_control_chars(c, true));
+    if let Some(content) = &content {
+        if content.len() > CONTENT_MAX_BYTES {
+            errors.push(FieldError::new(
+                "content",
+                format!("Content must be at most {} KB", CONTENT_MAX_BYTES / 1024),
+            ));
+        }
+    }
+
+    let tags = check_tags(&note.tags, &mut errors);
+
+    if !errors.is_empty() {
+        return Err(AppError::invalid_fields(errors));
+    }
+
+    Ok(Note {
+        title,
+        content,
+        tags,
+        ..note.clone()
+    })
+}
+
+/// Checks tags added to a note outside of a note save, with the same
+/// rules `validate_note` applies to `note.tags`.
+pub fn validate_tags(tags: &[String]) -> Result<Vec<String>, AppError> {
+    let mut errors = Vec::new();
+    let tags = check_tags(tags, &mut errors);
+    if !errors.is_empty() {
+        return Err(AppError::invalid_fields(errors));
+    }
+    Ok(tags)
+}
+
+/// Checks the new name of a renamed tag, reported against `field`.
+pub fn validate_tag_name(name: &str, field: &str) -> Result<String, AppError> {
+    let mut errors = Vec::new();
+    let name = check_tag(name, field, &mut errors);
+    if name.is_empty() {
+        errors.push(FieldError::new(field, "Tag name must not be empty"));
+    }
+    if !errors.is_empty() {
+        return Err(AppError::invalid_fields(errors));
+    }
+    Ok(name)
+}
+
+fn check_tags(tags: &[String], errors: &mut Vec<FieldError>) -> Vec<String> {
+    tags.iter()
+        .enumerate()
+        .map(|(i, tag)| check_tag(tag, &format!("tags[{}]", i), errors))
+        .collect()
+}
+
+fn check_tag(tag: &str, field: &str, errors: &mut Vec<FieldError>) -> String {
+    let tag = strip_control_chars(tag, false).trim().to_string();
+    if tag.chars().count() > TAG_MAX_CHARS {
+        errors.push(FieldError::new(
+            field,
+            format!("Tags must be at most {} characters", TAG_MAX_CHARS),
+        ));
+    }
+    tag
+}
+
+/// Removes control characters. Multi-line fields keep newlines and tabs;
+/// `\r\n` is normalized to `\n`.
+fn strip_control_chars(value: &str, multiline: bool) -> String {
+    value
+        .replace("\r\n", "\n")
+        .chars()
+        .filter(|&c| !c.is_control() || (multiline && (c == '\n' || c == '\t')))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: Option<&str>, tags: &[&str]) -> Note {
+        Note {
+            id: None,
+            title: title.to_string(),
+            content: content.map(String::from),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            version: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn fields(result: Result<Note, AppError>) -> Vec<String> {
+        match result {
+            Err(AppError::Validation { fields, .. }) => fields.into_iter().map(|f| f.field).collect(),
+            other => panic!("expected a validation error, got {:?}", other.map(|n| n.title)),
+        }
+    }
+
+    #[test]
+    fn titles_are_counted_in_characters() {
+        let at_limit = "é".repeat(TITLE_MAX_CHARS);
+        assert!(at_limit.len() > TITLE_MAX_CHARS);
+        assert_eq!(validate_note(&note(&at_limit, None, &[])).unwrap().title, at_limit);
+
+        let over = "é".repeat(TITLE_MAX_CHARS + 1);
+        assert_eq!(fields(validate_note(&note(&over, None, &[]))), ["title"]);
+    }
+
+    #[test]
+    fn content_is_counted_in_bytes() {
+        let at_limit = "a".repeat(CONTENT_MAX_BYTES);
+        assert!(validate_note(&note("t", Some(&at_limit), &[])).is_ok());
+
+        let over = "a".repeat(CONTENT_MAX_BYTES + 1);
+        assert_eq!(fields(validate_note(&note("t", Some(&over), &[]))), ["content"]);
+    }
+
+    #[test]
+    fn tags_are_limited_in_length() {
+        let at_limit = "t".repeat(TAG_MAX_CHARS);
+        let over = "t".repeat(TAG_MAX_CHARS + 1);
+        assert_eq!(validate_tags(std::slice::from_ref(&at_limit)).unwrap(), [at_limit.as_str()]);
+        assert_eq!(fields(validate_note(&note("t", None, &[&at_limit, &over]))), ["tags[1]"]);
+        assert!(validate_tag_name(&over, "name").is_err());
+    }
+
+    #[test]
+    fn every_bad_field_is_reported() {
+        let over = "t".repeat(TAG_MAX_CHARS + 1);
+        let result = validate_note(&note(" \u{7} ", Some(&"a".repeat(CONTENT_MAX_BYTES + 1)), &[&over]));
+        let Err(AppError::Validation { message, fields }) = result else {
+            panic!("expected a validation error");
+        };
+        let names: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(names, ["title", "content", "tags[0]"]);
+        assert!(message.contains("Title must not be empty"));
+    }
+
+    #[test]
+    fn strips_control_characters_but_keeps_newlines_and_tabs_in_content() {
+        let cleaned = validate_note(&note("a\tb\u{0}c\n", Some("one\r\n\ttwo\u{1b}"), &["x\ny"])).unwrap();
+        assert_eq!(cleaned.title, "abc");
+        assert_eq!(cleaned.content.as_deref(), Some("one\n\ttwo"));
+        assert_eq!(cleaned.tags, ["xy"]);
+    }
+
+    #[test]
+    fn empty_tag_names_are_rejected() {
+        assert_eq!(validate_tag_name("  renamed ", "name").unwrap(), "renamed");
+        let Err(AppError::Validation { fields, .. }) = validate_tag_name(" \u{7}", "name") else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(fields[0].field, "name");
+    }
+}
@@ -1,62 +1,123 @@
 // src/windows_setup.rs
 
-use crate::{detect_os, OperatingSystem};
+use crate::setup::{InstallationStage, SetupProgress, SetupStep, SystemSetup};
 use std::thread;
 use std::time::Duration;
 use anyhow::{Result, anyhow};
+use async_trait::async_trait;
 use tauri_plugin_shell::ShellExt;
-use tauri::Emitter;
 use tokio::process::Command;
 
-pub struct WindowsSystemSetup;
+/// Docker Desktop and a MySQL container on a named volume.
+pub struct WindowsSystemSetup {
+    app: tauri::AppHandle,
+}
 
 impl WindowsSystemSetup {
-    pub async fn setup_windows_system(app: &tauri::AppHandle) -> Result<()> {
-        app.emit("installation-stage", "CheckingDocker")
-            .map_err(|e| anyhow!("Event emission failed: {}", e))?;
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl SystemSetup for WindowsSystemSetup {
+    fn steps(&self) -> Vec<Box<dyn SetupStep>> {
+        vec![
+            Box::new(CheckSystem { app: self.app.clone() }),
+            Box::new(InstallDocker { app: self.app.clone() }),
+            Box::new(StartMySqlContainer { app: self.app.clone() }),
+        ]
+    }
+}
 
-        if detect_os() != OperatingSystem::Windows {
-            return Ok(());
-        }
+/// Docker Desktop needs a Pro, Enterprise or Education edition.
+struct CheckSystem {
+    app: tauri::AppHandle,
+}
 
-        // Check Windows version
-        if let Ok(false) = Self::check_windows_version(app).await {
-            app.emit("installation-stage", "DockerInstallFailed")
-                .map_err(|e| anyhow!("Event emission failed: {}", e))?;
+#[async_trait]
+impl SetupStep for CheckSystem {
+    fn name(&self) -> &'static str {
+        "check_system"
+    }
+
+    fn started(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::CheckingDocker)
+    }
+
+    fn failed(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::DockerInstallFailed)
+    }
+
+    async fn run(&self, _progress: &dyn SetupProgress) -> Result<()> {
+        if let Ok(false) = WindowsSystemSetup::check_windows_version(&self.app).await {
             return Err(anyhow!("Incompatible Windows version"));
         }
+        Ok(())
+    }
+}
 
-        // Docker check/install
-        if !Self::check_docker(app).await {
-            app.emit("installation-stage", "DockerNotInstalled")?;
-            app.emit("installation-stage", "DockerInstalling")?;
-            
-            Self::install_docker(app).await?;
-            app.emit("installation-stage", "DockerInstalled")?;
-        } else {
-            app.emit("installation-stage", "DockerInstalled")?;
+/// Installs Docker Desktop unless it already is, then checks it is running.
+struct InstallDocker {
+    app: tauri::AppHandle,
+}
+
+#[async_trait]
+impl SetupStep for InstallDocker {
+    fn name(&self) -> &'static str {
+        "install_docker"
+    }
+
+    fn completed(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::DockerInstalled)
+    }
+
+    fn failed(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::DockerInstallFailed)
+    }
+
+    async fn run(&self, progress: &dyn SetupProgress) -> Result<()> {
+        if !WindowsSystemSetup::check_docker(&self.app).await {
+            progress.stage(InstallationStage::DockerNotInstalled);
+            progress.stage(InstallationStage::DockerInstalling);
+            WindowsSystemSetup::install_docker(&self.app).await?;
         }
 
-        // Verify Docker running
-        if !Self::is_docker_running(app).await? {
+        if !WindowsSystemSetup::is_docker_running(&self.app).await? {
             return Err(anyhow!("Docker not running"));
         }
+        Ok(())
+    }
+}
 
-        // MySQL container setup
-        app.emit("installation-stage", "StartingMySQLContainer")?;
-        match Self::check_and_create_mysql_volume(app).await {
-            Ok(_) => app.emit("installation-stage", "MySQLContainerStarted")?,
-            Err(e) => {
-                app.emit("installation-stage", "MySQLSetupFailed")?;
-                return Err(e);
-            }
-        }
+/// Creates the data volume and runs the MySQL container on it.
+struct StartMySqlContainer {
+    app: tauri::AppHandle,
+}
 
-        app.emit("installation-stage", "SetupComplete")?;
-        Ok(())
+#[async_trait]
+impl SetupStep for StartMySqlContainer {
+    fn name(&self) -> &'static str {
+        "start_mysql_container"
+    }
+
+    fn started(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::StartingMySQLContainer)
+    }
+
+    fn completed(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::MySQLContainerStarted)
     }
 
+    fn failed(&self) -> Option<InstallationStage> {
+        Some(InstallationStage::MySQLSetupFailed)
+    }
+
+    async fn run(&self, _progress: &dyn SetupProgress) -> Result<()> {
+        WindowsSystemSetup::check_and_create_mysql_volume(&self.app).await
+    }
+}
 
+impl WindowsSystemSetup {
     async fn check_and_create_mysql_volume(app: &tauri::AppHandle) -> Result<()> {
         let volume_check = app.shell().command("docker")
             .args(["volume", "ls", "--filter", "name=mysql_data"])
@@ -80,14 +141,9 @@ impl WindowsSystemSetup {
                 "-p", "3306:3306", "mysql:8.0"
             ])
             .output()
-            .await
-            .map_err(|e| {
-                let _ = app.emit("installation-stage", "MySQLSetupFailed");
-                anyhow!(e)
-            })?;
+            .await?;
 
         if !output.status.success() {
-            let _ = app.emit("installation-stage", "MySQLSetupFailed");
             return Err(anyhow!("Container creation failed"));
         }
 
@@ -218,16 +274,11 @@ impl WindowsSystemSetup {
         let output = app.shell().command("powershell")
             .args(["-ExecutionPolicy", "Bypass", "-File", &script_path.to_string_lossy()])
             .output()
-            .await
-            .map_err(|e| {
-                let _ = app.emit("installation-stage", "DockerInstallFailed");
-                anyhow!(e)
-            })?;
+            .await?;
 
         let _ = tokio::fs::remove_file(script_path).await;
 
         if !output.status.success() {
-            let _ = app.emit("installation-stage", "DockerInstallFailed");
             return Err(anyhow!("Install script failed"));
         }
 
@@ -268,14 +319,9 @@ impl WindowsSystemSetup {
                 "mysql:8.0"
             ])
             .output()
-            .await
-            .map_err(|e| {
-                let _ = app.emit("installation-stage", "MySQLSetupFailed");
-                anyhow!(e)
-            })?;
+            .await?;
     
         if !output.status.success() {
-            let _ = app.emit("installation-stage", "MySQLSetupFailed");
             return Err(anyhow!("MySQL container failed"));
         }
     
@@ -1,6 +1,7 @@
 // src/lib/notes.ts
 
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import { useToast } from '@/components/ui/toast/use-toast'
 
 // Define the Note interface to match your Rust backend
@@ -8,9 +9,177 @@ export interface Note {
     id?: number | null
     title: string
     content: string | null
+    // RFC 3339 in UTC with microseconds, e.g. 2024-05-01T12:30:15.000000Z
     created_at?: string | null
     updated_at?: string | null
+    deleted_at?: string | null
+    version?: number | null
+    tags?: string[]
   }
+
+// Error codes returned by every backend command; see src-tauri/src/error.rs
+export type ErrorCode =
+  | 'NOT_FOUND'
+  | 'VALIDATION'
+  | 'CONFLICT'
+  | 'DATABASE_UNAVAILABLE'
+  | 'SETUP_REQUIRED'
+  | 'INTERNAL'
+
+export interface FieldError {
+    field: string
+    message: string
+  }
+
+export interface AppError {
+    code: ErrorCode
+    message: string
+    // Set on VALIDATION: one entry per offending payload field
+    fields: FieldError[]
+    // Set on CONFLICT from update_note: the note as currently stored
+    current: Note | null
+  }
+
+export function isAppError(error: unknown): error is AppError {
+  return error !== null && typeof error === 'object' &&
+         typeof (error as AppError).code === 'string' &&
+         typeof (error as AppError).message === 'string'
+}
+
+export function isConflictError(error: unknown): error is AppError & { current: Note } {
+  return isAppError(error) && error.code === 'CONFLICT' && error.current !== null
+}
+
+export function errorMessage(error: unknown): string {
+  return isAppError(error) ? error.message : String(error)
+}
+
+// Payload of the 'sync-status' event and of get_sync_status / sync_notes
+export interface SyncStatus {
+    phase: 'Idle' | 'Syncing' | 'Offline' | 'Error'
+    // Offline edits not yet pushed to MySQL
+    pending: number
+    // Pending edits MySQL rejected, e.g. as invalid; retried on every sync
+    failed: number
+    // Conflicts resolved in the last run; the losing copy is kept as a revision
+    conflicts: number
+    last_synced_at: string | null
+    last_error: string | null
+  }
+
+export function onSyncStatus(handler: (status: SyncStatus) => void): Promise<UnlistenFn> {
+  return listen<SyncStatus>('sync-status', (event) => handler(event.payload))
+}
+
+// Published whenever any window changes a note. Deleted notes are in the
+// trash (deleted_at set); a note restored from the trash is reported as created
+export type NoteChange = 'created' | 'updated' | 'deleted'
+
+export function onNoteChanged(handler: (change: NoteChange, note: Note) => void): Promise<UnlistenFn> {
+  const changes: NoteChange[] = ['created', 'updated', 'deleted']
+  return Promise.all(
+    changes.map(change => listen<Note>(`note-${change}`, (event) => handler(change, event.payload)))
+  ).then(unlisteners => () => unlisteners.forEach(unlisten => unlisten()))
+}
+
+// Published when any note may have changed at once, e.g. after a sync
+// replaced the local copy and swapped offline ids for MySQL ones. Reload
+// the notes rather than patching the list
+export function onNotesReloaded(handler: () => void): Promise<UnlistenFn> {
+  return listen('notes-reloaded', () => handler())
+}
+
+// Batches are all-or-nothing: every item is Ok, or at least one is Failed
+// and the rest RolledBack
+export interface BatchItemResult {
+    status: 'Ok' | 'Failed' | 'RolledBack'
+    id: number | null
+    note: Note | null
+    error: AppError | null
+  }
+
+export interface BatchResult {
+    committed: boolean
+    items: BatchItemResult[]
+  }
+
+// rows_affected is 0 when the note did not exist or was already deleted
+export interface DeleteResult {
+    id: number
+    rows_affected: number
+  }
+
+// Note content rendered from Markdown by the backend. The HTML is already
+// sanitized and can be bound with v-html
+export interface TocEntry {
+    level: number
+    text: string
+    // id of the heading in html
+    anchor: string
+  }
+
+export interface RenderedNote {
+    html: string
+    toc: TocEntry[]
+  }
+
+// A file attached to a note; contents are stored once per distinct sha256
+export interface Attachment {
+    id: number
+    note_id: number
+    file_name: string
+    mime_type: string
+    size_bytes: number
+    sha256: string
+    created_at: string | null
+  }
+
+export interface ExportSummary {
+    directory: string
+    files: string[]
+    // Files of renamed or trashed notes deleted from the directory
+    removed: string[]
+  }
+
+export interface ImportSummary {
+    created: number
+    updated: number
+    unchanged: number
+    // One entry per file (Markdown) or record (JSON, CSV) that could not be imported
+    errors: { source: string, message: string }[]
+  }
+
+export interface BulkExportSummary {
+    path: string
+    exported: number
+  }
+
+// Upsert updates notes whose id exists; Create always adds new notes
+export type ImportMode = 'Upsert' | 'Create'
+
+// Payload of the 'transfer-progress' event sent during JSON and CSV transfers
+export interface TransferProgress {
+    direction: 'Export' | 'Import'
+    format: 'Json' | 'Csv'
+    processed: number
+    // Only known up front for exports
+    total: number | null
+    done: boolean
+  }
+
+export function onTransferProgress(handler: (progress: TransferProgress) => void): Promise<UnlistenFn> {
+  return listen<TransferProgress>('transfer-progress', (event) => handler(event.payload))
+}
+
+const ERROR_TITLES: Record<ErrorCode, string> = {
+  NOT_FOUND: 'Not Found',
+  VALIDATION: 'Invalid Input',
+  CONFLICT: 'Edit Conflict',
+  DATABASE_UNAVAILABLE: 'Database Unavailable',
+  SETUP_REQUIRED: 'Setup Required',
+  INTERNAL: 'Error',
+}
+
 export class NoteService {
   private static toast = useToast()
 
@@ -21,6 +190,10 @@ export class NoteService {
            (obj as Note).title !== undefined
   }
 
+  private static errorTitle(error: unknown): string {
+    return isAppError(error) ? ERROR_TITLES[error.code] ?? 'Error' : 'Error'
+  }
+
   // Create a new note
   static async createNote(note: Note): Promise<Note> {
     try {
@@ -37,8 +210,8 @@ export class NoteService {
       return createdNote
     } catch (error) {
       this.toast.toast({
-        title: 'Error',
-        description: `Failed to create note: ${error}`,
+        title: this.errorTitle(error),
+        description: `Failed to create note: ${errorMessage(error)}`,
         variant: 'destructive',
       })
       throw error
@@ -57,8 +230,8 @@ export class NoteService {
       return notes
     } catch (error) {
       this.toast.toast({
-        title: 'Error',
-        description: `Failed to fetch notes: ${error}`,
+        title: this.errorTitle(error),
+        description: `Failed to fetch notes: ${errorMessage(error)}`,
         variant: 'destructive',
       })
       throw error
@@ -77,8 +250,8 @@ export class NoteService {
       return note
     } catch (error) {
       this.toast.toast({
-        title: 'Error',
-        description: `Failed to fetch note with id ${id}: ${error}`,
+        title: this.errorTitle(error),
+        description: `Failed to fetch note with id ${id}: ${errorMessage(error)}`,
         variant: 'destructive',
       })
       throw error
@@ -101,8 +274,8 @@ export class NoteService {
       return updatedNote
     } catch (error) {
       this.toast.toast({
-        title: 'Error',
-        description: `Failed to update note with id ${id}: ${error}`,
+        title: this.errorTitle(error),
+        description: `Failed to update note with id ${id}: ${errorMessage(error)}`,
         variant: 'destructive',
       })
       throw error
@@ -110,13 +283,16 @@ export class NoteService {
   }
 
   // Delete a note
-  static async deleteNote(id: number): Promise<boolean> {
+  static async deleteNote(id: number): Promise<DeleteResult> {
     try {
-      const result = await invoke('delete_note', { id }) as unknown
+      const result = await invoke('delete_note', { id }) as DeleteResult
       
-      if (typeof result !== 'boolean') {
+      if (!result || typeof result.rows_affected !== 'number') {
         throw new Error('Invalid delete result returned')
       }
+      if (result.rows_affected === 0) {
+        throw new Error('Note not found')
+      }
 
       this.toast.toast({
         title: 'Note Deleted',
@@ -125,11 +301,157 @@ export class NoteService {
       return result
     } catch (error) {
       this.toast.toast({
-        title: 'Error',
-        description: `Failed to delete note with id ${id}: ${error}`,
+        title: this.errorTitle(error),
+        description: `Failed to delete note with id ${id}: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  // Write every note to a directory of Markdown files
+  static async exportNotes(dir: string): Promise<ExportSummary> {
+    try {
+      const summary = await invoke('export_notes', { dir }) as ExportSummary
+
+      this.toast.toast({
+        title: 'Notes Exported',
+        description: `${summary.files.length} notes were written to ${summary.directory}.` +
+          (summary.removed.length ? ` ${summary.removed.length} outdated files were removed.` : ''),
+      })
+      return summary
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to export notes: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  // Read notes back from a directory of Markdown files
+  static async importNotes(dir: string, mode: ImportMode = 'Upsert'): Promise<ImportSummary> {
+    try {
+      const summary = await invoke('import_notes', { dir, mode }) as ImportSummary
+
+      this.toast.toast({
+        title: summary.errors.length ? 'Import Finished With Errors' : 'Notes Imported',
+        description: `${summary.created} created, ${summary.updated} updated, ${summary.unchanged} unchanged` +
+          (summary.errors.length ? `, ${summary.errors.length} failed.` : '.'),
+        variant: summary.errors.length ? 'destructive' : 'default',
+      })
+      return summary
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to import notes: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  // Create several notes in one transaction
+  static async batchCreateNotes(notes: Note[]): Promise<BatchResult> {
+    return this.runBatch('batch_create_notes', { notes }, 'created')
+  }
+
+  // Update several notes in one transaction; each note's tags are replaced
+  static async batchUpdateNotes(notes: Note[]): Promise<BatchResult> {
+    return this.runBatch('batch_update_notes', { notes }, 'updated')
+  }
+
+  // Move several notes to the trash in one transaction
+  static async batchDeleteNotes(ids: number[]): Promise<BatchResult> {
+    return this.runBatch('batch_delete_notes', { ids }, 'deleted')
+  }
+
+  // Render Markdown content the same way in every view
+  static async renderNoteHtml(content: string): Promise<RenderedNote> {
+    return await invoke('render_note_html', { content }) as RenderedNote
+  }
+
+  // Attach the file at `path` to a note
+  static async addAttachment(noteId: number, path: string): Promise<Attachment> {
+    try {
+      const attachment = await invoke('add_attachment', { noteId, path }) as Attachment
+
+      this.toast.toast({
+        title: 'Attachment Added',
+        description: `${attachment.file_name} was attached to the note.`,
+      })
+      return attachment
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to attach file: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  static async listAttachments(noteId: number): Promise<Attachment[]> {
+    return await invoke('list_attachments', { noteId }) as Attachment[]
+  }
+
+  // Open an attachment with the system's default app
+  static async openAttachment(id: number): Promise<void> {
+    try {
+      await invoke('open_attachment', { id })
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to open attachment: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  static async deleteAttachment(id: number): Promise<DeleteResult> {
+    try {
+      const result = await invoke('delete_attachment', { id }) as DeleteResult
+      if (result.rows_affected === 0) {
+        throw new Error('Attachment not found')
+      }
+      return result
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to delete attachment: ${errorMessage(error)}`,
+        variant: 'destructive',
+      })
+      throw error
+    }
+  }
+
+  private static async runBatch(command: string, args: Record<string, unknown>, verb: string): Promise<BatchResult> {
+    try {
+      const result = await invoke(command, args) as BatchResult
+
+      if (result.committed) {
+        this.toast.toast({
+          title: 'Notes Saved',
+          description: `${result.items.length} notes were ${verb}.`,
+        })
+      } else {
+        const failed = result.items.filter((item) => item.status === 'Failed')
+        this.toast.toast({
+          title: failed[0]?.error ? this.errorTitle(failed[0].error) : 'Error',
+          description: `No notes were ${verb}: ${failed.map((item) => errorMessage(item.error)).join('; ')}`,
+          variant: 'destructive',
+        })
+      }
+      return result
+    } catch (error) {
+      this.toast.toast({
+        title: this.errorTitle(error),
+        description: `Failed to run batch: ${errorMessage(error)}`,
         variant: 'destructive',
       })
       throw error
     }
   }
-}
\ No newline at end of file
+}
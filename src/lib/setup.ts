@@ -0,0 +1,52 @@
+// src/lib/setup.ts
+
+import { invoke } from '@tauri-apps/api/core'
+
+// Stages reported on the `installation-stage` event; see src-tauri/src/setup.rs
+export type SetupStage =
+  | 'NotStarted'
+  | 'CheckingDocker'
+  | 'DockerNotInstalled'
+  | 'DockerInstalling'
+  | 'DockerInstallFailed'
+  | 'DockerInstalled'
+  | 'PreparingMySQLContainer'
+  | 'StartingMySQLContainer'
+  | 'MySQLContainerStarted'
+  | 'MySQLSetupFailed'
+  | 'SetupComplete'
+
+// A step left 'Started' was interrupted and runs again next time
+export type StepOutcome = 'Started' | 'Completed' | 'Failed'
+
+export interface StepRecord {
+    name: string
+    outcome: StepOutcome
+    error: string | null
+    updated_at: string
+  }
+
+export interface SetupStatus {
+    // The last stage reported by the latest run
+    stage: SetupStage
+    steps: StepRecord[]
+    // null when setup has never run
+    updated_at: string | null
+  }
+
+// How start_system_setup treats an existing Docker install. 'Repair' only
+// adds what is missing; 'CleanReinstall' removes Docker after a confirmation
+export type DockerInstallMode = 'Repair' | 'CleanReinstall'
+
+// Payload of the `setup-confirm-request` event. Answer with `true` or
+// `false` on `setup-confirm-response-<request_id>`
+export interface ConfirmRequest {
+    request_id: string
+    message: string
+    details: string[]
+  }
+
+// Where the last setup run got to, as saved in the app data dir
+export async function getSetupStatus(): Promise<SetupStatus> {
+  return await invoke('get_setup_status') as SetupStatus
+}